@@ -0,0 +1,98 @@
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A wait status that is neither an exit, a signal, a stop nor a continue: `code()`
+/// and `signal()` are `None` and `success()` is false. Displayed as "unrecognised".
+const UNKNOWN_STATUS: i32 = 0xff;
+
+/// A QEMU process that is not our child, polled through a pidfd (which also guards
+/// against PID reuse), or with `kill(pid, 0)` where `pidfd_open(2)` is unavailable.
+///
+/// The exit status of a process we did not spawn cannot be collected, so once it is
+/// gone the reported status is an unknown one: no exit code, no signal, not success.
+#[derive(Debug)]
+pub(crate) struct AdoptedProcess {
+    pid: u32,
+    pidfd: Option<OwnedFd>,
+    exited: bool,
+}
+
+impl AdoptedProcess {
+    pub(crate) fn new(pid: u32) -> std::io::Result<Self> {
+        if !pid_alive(pid)? {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("process {} is not running", pid),
+            ));
+        }
+        // SAFETY: pidfd_open(2) takes no pointers; a non-negative result is a new fd we own.
+        let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+        let pidfd = if fd >= 0 {
+            // SAFETY: `fd` was just returned by pidfd_open and is not owned elsewhere.
+            Some(unsafe { OwnedFd::from_raw_fd(fd as i32) })
+        } else {
+            None
+        };
+        Ok(Self {
+            pid,
+            pidfd,
+            exited: false,
+        })
+    }
+
+    pub(crate) fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub(crate) fn is_exited(&self) -> bool {
+        self.exited
+    }
+
+    pub(crate) fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        if !self.exited {
+            self.exited = match &self.pidfd {
+                Some(fd) => pidfd_ready(fd)?,
+                None => !pid_alive(self.pid)?,
+            };
+        }
+        Ok(self.exited.then(|| ExitStatus::from_raw(UNKNOWN_STATUS)))
+    }
+
+    pub(crate) async fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Ok(status);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+fn pid_alive(pid: u32) -> std::io::Result<bool> {
+    // SAFETY: kill(2) with signal 0 only checks for existence and permission.
+    if unsafe { libc::kill(pid as libc::pid_t, 0) } == 0 {
+        return Ok(true);
+    }
+    let err = std::io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::ESRCH) => Ok(false),
+        _ => Err(err),
+    }
+}
+
+fn pidfd_ready(fd: &OwnedFd) -> std::io::Result<bool> {
+    let mut pollfd = libc::pollfd {
+        fd: fd.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // SAFETY: `pollfd` is a valid, initialised array of length 1 for the duration of the call.
+    match unsafe { libc::poll(&mut pollfd, 1, 0) } {
+        -1 => Err(std::io::Error::last_os_error()),
+        n => Ok(n > 0),
+    }
+}
@@ -0,0 +1,218 @@
+use std::path::{Path, PathBuf};
+
+/// `cpu.max`: `quota_us` of CPU time per `period_us`; no quota means unlimited.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct QemuCpuMax {
+    pub quota_us: Option<u64>,
+    pub period_us: u64,
+}
+
+impl QemuCpuMax {
+    /// A limit of `cpus` whole CPUs over the default 100ms period.
+    pub fn cpus(cpus: f64) -> Self {
+        let period_us = 100_000;
+        Self {
+            quota_us: Some((cpus * period_us as f64).round() as u64),
+            period_us,
+        }
+    }
+
+    pub(crate) fn to_cgroup_value(self) -> String {
+        match self.quota_us {
+            Some(quota) => format!("{} {}", quota, self.period_us),
+            None => format!("max {}", self.period_us),
+        }
+    }
+}
+
+/// One `io.max` line for a block device (`major:minor`). Unset limits are `max`.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct QemuIoMax {
+    pub major: u32,
+    pub minor: u32,
+    pub rbps: Option<u64>,
+    pub wbps: Option<u64>,
+    pub riops: Option<u64>,
+    pub wiops: Option<u64>,
+}
+
+impl QemuIoMax {
+    pub fn new(major: u32, minor: u32) -> Self {
+        Self {
+            major,
+            minor,
+            rbps: None,
+            wbps: None,
+            riops: None,
+            wiops: None,
+        }
+    }
+
+    pub fn with_bps(mut self, read: Option<u64>, write: Option<u64>) -> Self {
+        self.rbps = read;
+        self.wbps = write;
+        self
+    }
+
+    pub fn with_iops(mut self, read: Option<u64>, write: Option<u64>) -> Self {
+        self.riops = read;
+        self.wiops = write;
+        self
+    }
+
+    pub(crate) fn to_cgroup_value(self) -> String {
+        let limit = |v: Option<u64>| v.map_or("max".to_string(), |v| v.to_string());
+        format!(
+            "{}:{} rbps={} wbps={} riops={} wiops={}",
+            self.major,
+            self.minor,
+            limit(self.rbps),
+            limit(self.wbps),
+            limit(self.riops),
+            limit(self.wiops)
+        )
+    }
+}
+
+/// Resource limits written into a VM's cgroup. Unset fields are left at the kernel default.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub struct QemuResourceSpec {
+    cpu_max: Option<QemuCpuMax>,
+    memory_max: Option<u64>,
+    memory_high: Option<u64>,
+    io_max: Vec<QemuIoMax>,
+    pids_max: Option<u64>,
+}
+
+impl QemuResourceSpec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_cpu_max(mut self, cpu_max: QemuCpuMax) -> Self {
+        self.cpu_max = Some(cpu_max);
+        self
+    }
+
+    /// Hard memory limit in bytes; the kernel OOM-kills QEMU beyond it.
+    pub fn with_memory_max(mut self, bytes: u64) -> Self {
+        self.memory_max = Some(bytes);
+        self
+    }
+
+    /// Memory throttling threshold in bytes.
+    pub fn with_memory_high(mut self, bytes: u64) -> Self {
+        self.memory_high = Some(bytes);
+        self
+    }
+
+    pub fn with_io_max(mut self, io_max: QemuIoMax) -> Self {
+        self.io_max.push(io_max);
+        self
+    }
+
+    pub fn with_pids_max(mut self, pids: u64) -> Self {
+        self.pids_max = Some(pids);
+        self
+    }
+
+    pub fn get_cpu_max(&self) -> Option<QemuCpuMax> {
+        self.cpu_max
+    }
+
+    pub fn get_memory_max(&self) -> Option<u64> {
+        self.memory_max
+    }
+
+    pub fn get_memory_high(&self) -> Option<u64> {
+        self.memory_high
+    }
+
+    pub fn get_io_max(&self) -> &[QemuIoMax] {
+        &self.io_max
+    }
+
+    pub fn get_pids_max(&self) -> Option<u64> {
+        self.pids_max
+    }
+
+    /// `(controller, file, value)` for every limit that is set.
+    pub(crate) fn to_cgroup_files(&self) -> Vec<(&'static str, &'static str, String)> {
+        let mut files = Vec::new();
+        if let Some(cpu) = self.cpu_max {
+            files.push(("cpu", "cpu.max", cpu.to_cgroup_value()));
+        }
+        if let Some(bytes) = self.memory_high {
+            files.push(("memory", "memory.high", bytes.to_string()));
+        }
+        if let Some(bytes) = self.memory_max {
+            files.push(("memory", "memory.max", bytes.to_string()));
+        }
+        for io in &self.io_max {
+            files.push(("io", "io.max", io.to_cgroup_value()));
+        }
+        if let Some(pids) = self.pids_max {
+            files.push(("pids", "pids.max", pids.to_string()));
+        }
+        files
+    }
+}
+
+/// Puts the launched QEMU into its own cgroup v2 below `parent`, which has to be a
+/// subtree delegated to this process.
+///
+/// Unless `required`, problems setting up the cgroup (no delegation, missing
+/// controllers) are logged and QEMU runs with whatever could be applied. Once the
+/// cgroup exists, QEMU failing to join it always fails the launch.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuCgroupConfig {
+    parent: PathBuf,
+    name: Option<String>,
+    resources: QemuResourceSpec,
+    required: bool,
+}
+
+impl QemuCgroupConfig {
+    pub fn new(parent: impl Into<PathBuf>) -> Self {
+        Self {
+            parent: parent.into(),
+            name: None,
+            resources: QemuResourceSpec::default(),
+            required: false,
+        }
+    }
+
+    /// Name of the per-VM cgroup; defaults to `qemu-<pid>-<n>`, with the pid of this
+    /// process.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_resources(mut self, resources: QemuResourceSpec) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    /// Fail the launch instead of degrading when the cgroup cannot be set up fully.
+    pub fn with_required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    pub fn get_parent(&self) -> &Path {
+        &self.parent
+    }
+
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn get_resources(&self) -> &QemuResourceSpec {
+        &self.resources
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+}
@@ -0,0 +1,5 @@
+mod cgroup_config;
+mod qemu_cgroup;
+
+pub use cgroup_config::{QemuCgroupConfig, QemuCpuMax, QemuIoMax, QemuResourceSpec};
+pub use qemu_cgroup::{QemuCgroup, QemuCgroupStats};
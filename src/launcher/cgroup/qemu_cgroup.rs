@@ -0,0 +1,225 @@
+use std::os::fd::OwnedFd;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use super::QemuCgroupConfig;
+
+/// How often `remove` retries while the kernel still reports the cgroup as busy.
+const REMOVE_ATTEMPTS: usize = 10;
+const REMOVE_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+static NEXT_CGROUP_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Usage counters of a VM's cgroup. Fields are `None` when the controller providing
+/// them is not enabled.
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct QemuCgroupStats {
+    pub cpu_usage_usec: Option<u64>,
+    pub cpu_user_usec: Option<u64>,
+    pub cpu_system_usec: Option<u64>,
+    pub cpu_nr_throttled: Option<u64>,
+    pub cpu_throttled_usec: Option<u64>,
+    pub memory_current: Option<u64>,
+    pub memory_peak: Option<u64>,
+    pub pids_current: Option<u64>,
+}
+
+/// The cgroup v2 a QEMU process runs in. `QemuProcess` creates it before spawning
+/// QEMU, which joins it before exec, so nothing QEMU does (including forking for
+/// `-daemonize`) happens outside of it. Removed once the process has been reaped (or
+/// on drop, if it is empty by then).
+#[derive(Debug)]
+pub struct QemuCgroup {
+    path: PathBuf,
+    skipped: Vec<String>,
+    removed: bool,
+}
+
+impl QemuCgroup {
+    /// Creates the cgroup below the configured parent, enables the controllers the
+    /// limits need and writes the limits. Processes are added with `open_procs`.
+    ///
+    /// Controllers or limits that cannot be applied are recorded in `skipped` (or make
+    /// this fail if the config is `required`).
+    pub async fn create(config: &QemuCgroupConfig) -> std::io::Result<Self> {
+        let parent = config.get_parent();
+        let available = match tokio::fs::read_to_string(parent.join("cgroup.controllers")).await {
+            Ok(list) => list,
+            Err(e) => {
+                return Err(std::io::Error::new(
+                    e.kind(),
+                    format!("{} is not a cgroup v2 directory: {}", parent.display(), e),
+                ));
+            }
+        };
+        let available: Vec<&str> = available.split_whitespace().collect();
+        let enabled = tokio::fs::read_to_string(parent.join("cgroup.subtree_control"))
+            .await
+            .unwrap_or_default();
+
+        let files = config.get_resources().to_cgroup_files();
+        let mut skipped = Vec::new();
+        let mut usable: Vec<&str> = Vec::new();
+        let mut unusable: Vec<&str> = Vec::new();
+        for (controller, _, _) in &files {
+            if usable.contains(controller) || unusable.contains(controller) {
+                continue;
+            }
+            if enabled.split_whitespace().any(|c| c == *controller) {
+                usable.push(controller);
+                continue;
+            }
+            if !available.contains(controller) {
+                unusable.push(controller);
+                skipped.push(format!(
+                    "{}: controller not available in {}",
+                    controller,
+                    parent.display()
+                ));
+                continue;
+            }
+            match tokio::fs::write(
+                parent.join("cgroup.subtree_control"),
+                format!("+{}", controller),
+            )
+            .await
+            {
+                Ok(()) => usable.push(controller),
+                Err(e) => {
+                    unusable.push(controller);
+                    skipped.push(format!("{}: cannot enable controller: {}", controller, e));
+                }
+            }
+        }
+
+        let name = config.get_name().map(str::to_string).unwrap_or_else(|| {
+            let id = NEXT_CGROUP_ID.fetch_add(1, Ordering::Relaxed);
+            format!("qemu-{}-{}", std::process::id(), id)
+        });
+        let path = parent.join(name);
+        tokio::fs::create_dir(&path).await.map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!("cannot create cgroup {}: {}", path.display(), e),
+            )
+        })?;
+        let mut cgroup = Self {
+            path,
+            skipped,
+            removed: false,
+        };
+
+        for (controller, file, value) in files {
+            if !usable.contains(&controller) {
+                continue;
+            }
+            if let Err(e) = tokio::fs::write(cgroup.path.join(file), &value).await {
+                cgroup
+                    .skipped
+                    .push(format!("{}: cannot write {:?}: {}", file, value, e));
+            }
+        }
+        if config.is_required() && !cgroup.skipped.is_empty() {
+            let _ = cgroup.remove().await;
+            return Err(std::io::Error::other(format!(
+                "cgroup limits could not be applied: {}",
+                cgroup.skipped.join("; ")
+            )));
+        }
+        Ok(cgroup)
+    }
+
+    /// Opens `cgroup.procs` for writing; a process writing `0` to it joins the cgroup.
+    pub(crate) fn open_procs(&self) -> std::io::Result<OwnedFd> {
+        let path = self.path.join("cgroup.procs");
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .map_err(|e| {
+                std::io::Error::new(e.kind(), format!("cannot open {}: {}", path.display(), e))
+            })?;
+        Ok(file.into())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Controllers and limits that could not be applied, with the reason.
+    pub fn skipped(&self) -> &[String] {
+        &self.skipped
+    }
+
+    pub async fn stats(&self) -> std::io::Result<QemuCgroupStats> {
+        let mut stats = QemuCgroupStats::default();
+        if let Some(cpu) = self.read_file("cpu.stat").await? {
+            for line in cpu.lines() {
+                let mut parts = line.split_whitespace();
+                let (Some(key), Some(value)) = (parts.next(), parts.next()) else {
+                    continue;
+                };
+                let value = value.parse().ok();
+                match key {
+                    "usage_usec" => stats.cpu_usage_usec = value,
+                    "user_usec" => stats.cpu_user_usec = value,
+                    "system_usec" => stats.cpu_system_usec = value,
+                    "nr_throttled" => stats.cpu_nr_throttled = value,
+                    "throttled_usec" => stats.cpu_throttled_usec = value,
+                    _ => {}
+                }
+            }
+        }
+        stats.memory_current = self.read_number("memory.current").await?;
+        stats.memory_peak = self.read_number("memory.peak").await?;
+        stats.pids_current = self.read_number("pids.current").await?;
+        Ok(stats)
+    }
+
+    async fn read_file(&self, file: &str) -> std::io::Result<Option<String>> {
+        match tokio::fs::read_to_string(self.path.join(file)).await {
+            Ok(text) => Ok(Some(text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn read_number(&self, file: &str) -> std::io::Result<Option<u64>> {
+        Ok(self
+            .read_file(file)
+            .await?
+            .and_then(|text| text.trim().parse().ok()))
+    }
+
+    /// Removes the (empty) cgroup, retrying briefly while the kernel finishes
+    /// tearing down the exited process.
+    pub async fn remove(&mut self) -> std::io::Result<()> {
+        if self.removed {
+            return Ok(());
+        }
+        let mut attempt = 0;
+        loop {
+            match tokio::fs::remove_dir(&self.path).await {
+                Ok(()) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+                Err(e)
+                    if e.raw_os_error() == Some(libc::EBUSY) && attempt + 1 < REMOVE_ATTEMPTS =>
+                {
+                    attempt += 1;
+                    tokio::time::sleep(REMOVE_RETRY_DELAY).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        self.removed = true;
+        Ok(())
+    }
+}
+
+impl Drop for QemuCgroup {
+    fn drop(&mut self) {
+        if !self.removed {
+            let _ = std::fs::remove_dir(&self.path);
+        }
+    }
+}
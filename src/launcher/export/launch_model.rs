@@ -0,0 +1,153 @@
+use std::io::{Error, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+
+use crate::launcher::preflight::unix_sockets;
+use crate::launcher::process::launch_argv;
+use crate::launcher::qemu_args::{canonical_option_name, shell_quote};
+use crate::launcher::{
+    QemuArg, QemuLaunchArgs, QemuLaunchOptions, QemuProcessGroup, QemuRlimit, QemuRlimitResource,
+};
+
+/// What `QemuProcess::launch_with_options` does for a pair of args and options, in
+/// the form the unit and script exporters render.
+pub(crate) struct LaunchModel {
+    pub argv: Vec<String>,
+    /// Set when QEMU is started with `-daemonize` (which requires a `-pidfile`).
+    pub pidfile: Option<String>,
+    pub qmp_socket: Option<PathBuf>,
+    /// Directories below `/run` that have to exist before QEMU starts.
+    pub runtime_directories: Vec<String>,
+}
+
+impl LaunchModel {
+    pub fn new(
+        args: &QemuLaunchArgs,
+        options: &QemuLaunchOptions,
+        qmp_socket: Option<&Path>,
+        runtime_directories: &[String],
+    ) -> std::io::Result<Self> {
+        if !options.get_passed_fds().is_empty() {
+            return Err(unsupported("passed file descriptors"));
+        }
+        if let QemuProcessGroup::Join(_) = options.get_process_group() {
+            return Err(unsupported("joining an existing process group"));
+        }
+        let pidfile = match args.is_daemonized() {
+            true => Some(
+                args.get_pidfile()
+                    .ok_or_else(|| unsupported("-daemonize without -pidfile"))?
+                    .to_string(),
+            ),
+            false => None,
+        };
+        let qmp_socket = qmp_socket
+            .map(Path::to_path_buf)
+            .or_else(|| find_qmp_socket(args));
+
+        let mut directories = runtime_directories.to_vec();
+        let paths = unix_sockets(args)
+            .into_iter()
+            .map(|(path, _)| PathBuf::from(path))
+            .chain(args.get_pidfile().map(PathBuf::from))
+            .chain(qmp_socket.clone());
+        for path in paths {
+            if let Ok(relative) = path.strip_prefix("/run")
+                && let Some(parent) = relative.parent()
+                && let Some(Component::Normal(first)) = parent.components().next()
+            {
+                let name = first.to_string_lossy().into_owned();
+                if !directories.contains(&name) {
+                    directories.push(name);
+                }
+            }
+        }
+
+        Ok(Self {
+            argv: launch_argv(args, options),
+            pidfile,
+            qmp_socket,
+            runtime_directories: directories,
+        })
+    }
+}
+
+fn unsupported(what: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("{} cannot be exported", what),
+    )
+}
+
+/// The path of a QMP monitor socket QEMU listens on: `-qmp unix:<path>,server...`,
+/// or a `-mon chardev=<id>,mode=control` on a `-chardev socket,...,server=on`.
+fn find_qmp_socket(args: &QemuLaunchArgs) -> Option<PathBuf> {
+    let option = |arg: &QemuArg| canonical_option_name(arg.key()).to_string();
+    for arg in args.get_args() {
+        if matches!(option(arg).as_str(), "-qmp" | "-qmp-pretty")
+            && let Some(value) = arg.to_args().get(1)
+            && let Some(rest) = value.strip_prefix("unix:")
+        {
+            let path = match QemuArg::from_opts("-qmp", rest) {
+                QemuArg::List(_, items) => items[0].clone(),
+                other => other.to_args()[1].clone(),
+            };
+            return Some(PathBuf::from(path.replace(",,", ",")));
+        }
+        if option(arg) == "-mon"
+            && arg.get_property("mode").as_deref() == Some("control")
+            && let Some(id) = arg.get_property("chardev")
+            && let Some(chardev) = args.get_arg_by_id("-chardev", &id)
+            && chardev.get_property("backend").as_deref() == Some("socket")
+            && let Some(path) = chardev.get_property("path")
+        {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+/// Shell commands that ask the guest to power down through the QMP socket.
+pub(crate) fn powerdown_command(qmp_socket: &Path) -> String {
+    format!(
+        "printf '%s\\n' '{{\"execute\":\"qmp_capabilities\"}}' '{{\"execute\":\"system_powerdown\"}}' | socat - {} >/dev/null",
+        shell_quote(&format!("UNIX-CONNECT:{}", qmp_socket.display()), false)
+    )
+}
+
+/// The VM name from `-name <name>` or `-name guest=<name>`.
+pub(crate) fn guest_name(args: &QemuLaunchArgs) -> Option<String> {
+    let arg = args
+        .get_args()
+        .iter()
+        .find(|a| canonical_option_name(a.key()) == "-name")?;
+    if let Some(name) = arg.get_property("guest") {
+        return Some(name);
+    }
+    let value = arg.to_args().get(1)?.clone();
+    let first = match QemuArg::from_opts("-name", &value) {
+        QemuArg::List(_, items) => items[0].clone(),
+        _ => value,
+    };
+    (!first.contains('=')).then(|| first.replace(",,", ","))
+}
+
+/// The `prlimit` option and systemd `Limit*=` suffix of a resource.
+pub(crate) fn rlimit_names(resource: QemuRlimitResource) -> (&'static str, &'static str) {
+    match resource {
+        QemuRlimitResource::AddressSpace => ("as", "AS"),
+        QemuRlimitResource::Core => ("core", "CORE"),
+        QemuRlimitResource::Cpu => ("cpu", "CPU"),
+        QemuRlimitResource::Data => ("data", "DATA"),
+        QemuRlimitResource::FileSize => ("fsize", "FSIZE"),
+        QemuRlimitResource::MemLock => ("memlock", "MEMLOCK"),
+        QemuRlimitResource::NoFile => ("nofile", "NOFILE"),
+        QemuRlimitResource::NProc => ("nproc", "NPROC"),
+        QemuRlimitResource::Stack => ("stack", "STACK"),
+    }
+}
+
+/// `soft:hard`, with `unlimited` for `None`.
+pub(crate) fn rlimit_value(limit: &QemuRlimit, unlimited: &str) -> String {
+    let value = |v: Option<u64>| v.map_or(unlimited.to_string(), |v| v.to_string());
+    format!("{}:{}", value(limit.get_soft()), value(limit.get_hard()))
+}
@@ -0,0 +1,314 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::launch_model::{LaunchModel, guest_name, powerdown_command, rlimit_names, rlimit_value};
+use crate::launcher::qemu_args::shell_quote;
+use crate::launcher::{QemuLaunchArgs, QemuLaunchOptions, QemuProcessGroup, QemuStdio};
+
+/// Renders a standalone bash script that runs QEMU the way
+/// `QemuProcess::launch_with_options` would, for hosts without systemd.
+///
+/// Unless QEMU daemonizes, the script stays in the foreground and waits for it. On
+/// SIGTERM or SIGINT it sends `system_powerdown` over the QMP socket and gives QEMU
+/// the stop timeout before sending SIGTERM; a second signal sends SIGTERM right away.
+/// The script exits with QEMU's status. Needs `prlimit`, `setpriv`, `setsid` and
+/// `socat` only when the options use them.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuLaunchScript {
+    qmp_socket: Option<PathBuf>,
+    stop_timeout: Duration,
+    runtime_directories: Vec<String>,
+}
+
+impl Default for QemuLaunchScript {
+    fn default() -> Self {
+        Self {
+            qmp_socket: None,
+            stop_timeout: Duration::from_secs(30),
+            runtime_directories: Vec::new(),
+        }
+    }
+}
+
+impl QemuLaunchScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The socket used to power the guest down; found in `-qmp unix:...` or a control
+    /// `-mon` if unset.
+    pub fn with_qmp_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.qmp_socket = Some(path.into());
+        self
+    }
+
+    pub fn with_stop_timeout(mut self, timeout: Duration) -> Self {
+        self.stop_timeout = timeout;
+        self
+    }
+
+    /// Adds a directory below `/run` the script creates. Directories of sockets and
+    /// pidfiles below `/run` are added automatically.
+    pub fn with_runtime_directory(mut self, name: impl Into<String>) -> Self {
+        self.runtime_directories.push(name.into());
+        self
+    }
+
+    pub fn get_qmp_socket(&self) -> Option<&Path> {
+        self.qmp_socket.as_deref()
+    }
+
+    pub fn get_stop_timeout(&self) -> Duration {
+        self.stop_timeout
+    }
+
+    pub fn get_runtime_directories(&self) -> &[String] {
+        &self.runtime_directories
+    }
+
+    /// Renders the script. Variables named with `with_env_inherit` are taken from the
+    /// environment the script runs in. The parent death signal is ignored; passed
+    /// descriptors and joining a process group cannot be expressed and are an
+    /// `InvalidInput` error.
+    pub fn render(
+        &self,
+        args: &QemuLaunchArgs,
+        options: &QemuLaunchOptions,
+    ) -> std::io::Result<String> {
+        let model = LaunchModel::new(
+            args,
+            options,
+            self.qmp_socket.as_deref(),
+            &self.runtime_directories,
+        )?;
+        let quote = |word: &str| shell_quote(word, false);
+        let path = |path: &Path| quote(&path.display().to_string());
+        let mut script = String::new();
+        let mut line = |text: String| {
+            if text.is_empty() && script.ends_with("\n\n") {
+                return;
+            }
+            script.push_str(&text);
+            script.push('\n');
+        };
+
+        line("#!/bin/bash".to_string());
+        let description = match guest_name(args) {
+            Some(name) => format!("QEMU virtual machine {}", name.replace('\n', " ")),
+            None => "QEMU virtual machine".to_string(),
+        };
+        line(format!("# {}", description));
+        line("set -euo pipefail".to_string());
+        line(String::new());
+
+        if let Some(umask) = options.get_umask() {
+            line(format!("umask {:04o}", umask));
+        }
+        for directory in &model.runtime_directories {
+            let directory = quote(&format!("/run/{}", directory));
+            line(format!("mkdir -p {}", directory));
+            match (options.get_uid(), options.get_gid()) {
+                (Some(uid), Some(gid)) => line(format!("chown {}:{} {}", uid, gid, directory)),
+                (Some(uid), None) => line(format!("chown {} {}", uid, directory)),
+                (None, Some(gid)) => line(format!("chgrp {} {}", gid, directory)),
+                (None, None) => {}
+            }
+        }
+        if let Some(dir) = options.get_current_dir() {
+            line(format!("cd {}", path(dir)));
+        }
+        for limit in options.get_rlimits() {
+            let (name, _) = rlimit_names(limit.get_resource());
+            line(format!(
+                "prlimit --pid $$ --{}={}",
+                name,
+                rlimit_value(limit, "unlimited")
+            ));
+        }
+
+        // The cgroup is joined by QEMU (or the script it replaces) right before exec,
+        // so that the script itself can remove it afterwards.
+        let cgroup = options.get_cgroup();
+        if let Some(cgroup) = cgroup {
+            line(String::new());
+            line("cgroup_failed() {".to_string());
+            match cgroup.is_required() {
+                true => {
+                    line("    echo \"$0: $1\" >&2".to_string());
+                    line("    exit 1".to_string());
+                }
+                false => line("    echo \"$0: warning: $1\" >&2".to_string()),
+            }
+            line("}".to_string());
+            line(format!("parent={}", path(cgroup.get_parent())));
+            let files = cgroup.get_resources().to_cgroup_files();
+            let mut controllers: Vec<&str> = Vec::new();
+            for (controller, _, _) in &files {
+                if !controllers.contains(controller) {
+                    controllers.push(controller);
+                }
+            }
+            if !controllers.is_empty() {
+                line(format!("for controller in {}; do", controllers.join(" ")));
+                line(
+                    "    grep -qw \"$controller\" \"$parent/cgroup.subtree_control\" ||"
+                        .to_string(),
+                );
+                line(
+                    "        echo \"+$controller\" >\"$parent/cgroup.subtree_control\" ||"
+                        .to_string(),
+                );
+                line(
+                    "        cgroup_failed \"cannot enable the $controller controller in $parent\""
+                        .to_string(),
+                );
+                line("done".to_string());
+            }
+            match cgroup.get_name() {
+                Some(name) => line(format!("cgroup=\"$parent\"/{}", quote(name))),
+                None => line("cgroup=\"$parent/qemu-$$\"".to_string()),
+            }
+            line("if mkdir \"$cgroup\"; then".to_string());
+            for (_, file, value) in &files {
+                line(format!(
+                    "    echo {} >\"$cgroup/{}\" || cgroup_failed \"cannot set {}\"",
+                    quote(value),
+                    file,
+                    file
+                ));
+            }
+            line("else".to_string());
+            line("    cgroup_failed \"cannot create $cgroup\"".to_string());
+            line("    cgroup=".to_string());
+            line("fi".to_string());
+        }
+
+        line(String::new());
+        let mut prefix: Vec<String> = Vec::new();
+        if options.get_process_group() == QemuProcessGroup::NewSession {
+            prefix.push("setsid -w".to_string());
+        }
+        if options.get_uid().is_some() || options.get_gid().is_some() {
+            let mut setpriv = "setpriv".to_string();
+            if let Some(gid) = options.get_gid() {
+                let _ = write!(setpriv, " --regid={}", gid);
+            }
+            if let Some(uid) = options.get_uid() {
+                let _ = write!(setpriv, " --reuid={}", uid);
+            }
+            setpriv.push_str(" --clear-groups --");
+            prefix.push(setpriv);
+        }
+        if options.get_env_clear() {
+            line("env=()".to_string());
+            for key in options.get_env_inherit() {
+                line(format!("[[ -v {0} ]] && env+=(\"{0}=${0}\")", key));
+            }
+            let mut env = "env -i ${env[@]+\"${env[@]}\"}".to_string();
+            for (key, value) in options.get_envs() {
+                let _ = write!(env, " {}", quote(&format!("{}={}", key, value)));
+            }
+            env.push_str(" --");
+            prefix.push(env);
+        } else {
+            for key in options.get_env_remove() {
+                line(format!("unset {}", key));
+            }
+            for (key, value) in options.get_envs() {
+                line(format!("export {}={}", key, quote(value)));
+            }
+        }
+
+        let qemu: Vec<String> = model.argv.iter().map(|a| quote(a)).collect();
+        line("qemu=(".to_string());
+        for word in qemu {
+            line(format!("    {}", word));
+        }
+        line(")".to_string());
+
+        let mut redirects = String::new();
+        for (fd, stdio) in [("", options.get_stdout()), ("2", options.get_stderr())] {
+            match stdio {
+                QemuStdio::Null => {
+                    let _ = write!(redirects, " {}>/dev/null", fd);
+                }
+                QemuStdio::File(file) => {
+                    let _ = write!(redirects, " {}>>{}", fd, path(file));
+                }
+                QemuStdio::Pipe(Some(log)) => {
+                    let _ = write!(redirects, " {}>>{}", fd, path(log.get_path()));
+                }
+                QemuStdio::Inherit | QemuStdio::Pipe(None) => {}
+            }
+        }
+        let mut command = prefix.join(" ");
+        if !command.is_empty() {
+            command.push(' ');
+        }
+        command.push_str("\"${qemu[@]}\"");
+        command.push_str(&redirects);
+        let join = match cgroup {
+            Some(_) => {
+                "[[ -z $cgroup ]] || echo \"$BASHPID\" >\"$cgroup/cgroup.procs\" || cgroup_failed \"cannot join $cgroup\""
+            }
+            None => "",
+        };
+
+        // A daemonizing QEMU leaves on its own, so the script has nothing to wait for.
+        if model.pidfile.is_some() {
+            if !join.is_empty() {
+                line(join.to_string());
+            }
+            line(format!("exec {}", command));
+            return Ok(script);
+        }
+
+        if options.get_process_group() == QemuProcessGroup::NewGroup {
+            // Job control puts background jobs into their own process group.
+            line("set -m".to_string());
+        }
+        match join.is_empty() {
+            true => line(format!("{} &", command)),
+            false => {
+                line("(".to_string());
+                line(format!("    {}", join));
+                line(format!("    exec {}", command));
+                line(") &".to_string());
+            }
+        }
+        line("pid=$!".to_string());
+        if options.get_process_group() == QemuProcessGroup::NewGroup {
+            line("set +m".to_string());
+        }
+        line("timer=".to_string());
+        line("stop() {".to_string());
+        line("    trap 'kill -TERM \"$pid\" 2>/dev/null || true' TERM INT".to_string());
+        match &model.qmp_socket {
+            Some(socket) => {
+                line(format!(
+                    "    {} || kill -TERM \"$pid\" 2>/dev/null || true",
+                    powerdown_command(socket)
+                ));
+                line(format!(
+                    "    {{ sleep {}; kill -TERM \"$pid\" 2>/dev/null; }} &",
+                    self.stop_timeout.as_secs_f64()
+                ));
+                line("    timer=$!".to_string());
+            }
+            None => line("    kill -TERM \"$pid\" 2>/dev/null || true".to_string()),
+        }
+        line("}".to_string());
+        line("trap stop TERM INT".to_string());
+        line("while :; do".to_string());
+        line("    wait \"$pid\" && status=0 || status=$?".to_string());
+        line("    kill -0 \"$pid\" 2>/dev/null || break".to_string());
+        line("done".to_string());
+        line("[[ -z $timer ]] || kill \"$timer\" 2>/dev/null || true".to_string());
+        if cgroup.is_some() {
+            line("[[ -z $cgroup ]] || rmdir \"$cgroup\" 2>/dev/null || true".to_string());
+        }
+        line("exit \"$status\"".to_string());
+        Ok(script)
+    }
+}
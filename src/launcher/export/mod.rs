@@ -0,0 +1,6 @@
+mod launch_model;
+mod launch_script;
+mod systemd_unit;
+
+pub use launch_script::QemuLaunchScript;
+pub use systemd_unit::QemuSystemdUnit;
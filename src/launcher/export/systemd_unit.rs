@@ -0,0 +1,272 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::launch_model::{LaunchModel, guest_name, powerdown_command, rlimit_names, rlimit_value};
+use crate::launcher::qemu_args::systemd_quote;
+use crate::launcher::{QemuLaunchArgs, QemuLaunchOptions, QemuStdio};
+
+/// Renders a systemd service that runs QEMU the way `QemuProcess::launch_with_options`
+/// would, for VMs that have to outlive the orchestrator.
+///
+/// Stopping the service sends `system_powerdown` over the QMP socket and waits for
+/// QEMU to exit; after `TimeoutStopSec` systemd sends SIGTERM to QEMU and finally
+/// SIGKILL (`KillMode=mixed`). Without a QMP socket QEMU just gets SIGTERM.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuSystemdUnit {
+    description: Option<String>,
+    qmp_socket: Option<PathBuf>,
+    stop_timeout: Duration,
+    runtime_directories: Vec<String>,
+    wanted_by: String,
+}
+
+impl Default for QemuSystemdUnit {
+    fn default() -> Self {
+        Self {
+            description: None,
+            qmp_socket: None,
+            stop_timeout: Duration::from_secs(30),
+            runtime_directories: Vec::new(),
+            wanted_by: "multi-user.target".to_string(),
+        }
+    }
+}
+
+impl QemuSystemdUnit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defaults to "QEMU virtual machine" and the `-name` of the VM.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// The socket `ExecStop=` uses; found in `-qmp unix:...` or a control `-mon` if unset.
+    pub fn with_qmp_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.qmp_socket = Some(path.into());
+        self
+    }
+
+    pub fn with_stop_timeout(mut self, timeout: Duration) -> Self {
+        self.stop_timeout = timeout;
+        self
+    }
+
+    /// Adds a `RuntimeDirectory=` (relative to `/run`). Directories of sockets and
+    /// pidfiles below `/run` are added automatically.
+    pub fn with_runtime_directory(mut self, name: impl Into<String>) -> Self {
+        self.runtime_directories.push(name.into());
+        self
+    }
+
+    pub fn with_wanted_by(mut self, target: impl Into<String>) -> Self {
+        self.wanted_by = target.into();
+        self
+    }
+
+    pub fn get_description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn get_qmp_socket(&self) -> Option<&Path> {
+        self.qmp_socket.as_deref()
+    }
+
+    pub fn get_stop_timeout(&self) -> Duration {
+        self.stop_timeout
+    }
+
+    pub fn get_runtime_directories(&self) -> &[String] {
+        &self.runtime_directories
+    }
+
+    pub fn get_wanted_by(&self) -> &str {
+        &self.wanted_by
+    }
+
+    /// Renders the unit file. Variables named with `with_env_inherit` are captured
+    /// from the current environment, since a service does not inherit it. The parent
+    /// death signal is ignored; passed descriptors and joining a process group
+    /// cannot be expressed and are an `InvalidInput` error.
+    pub fn render(
+        &self,
+        args: &QemuLaunchArgs,
+        options: &QemuLaunchOptions,
+    ) -> std::io::Result<String> {
+        let model = LaunchModel::new(
+            args,
+            options,
+            self.qmp_socket.as_deref(),
+            &self.runtime_directories,
+        )?;
+        let description = match (&self.description, guest_name(args)) {
+            (Some(description), _) => description.clone(),
+            (None, Some(name)) => format!("QEMU virtual machine {}", name),
+            (None, None) => "QEMU virtual machine".to_string(),
+        };
+
+        let mut service: Vec<(String, String)> = Vec::new();
+        let mut line = |key: &str, value: &str| service.push((key.to_string(), value.to_string()));
+        line(
+            "Type",
+            match &model.pidfile {
+                Some(_) => "forking",
+                None => "simple",
+            },
+        );
+        if let Some(pidfile) = &model.pidfile {
+            line("PIDFile", &escape_specifiers(pidfile));
+        }
+        let exec_start: Vec<String> = model.argv.iter().map(|a| systemd_quote(a)).collect();
+        line("ExecStart", &exec_start.join(" "));
+        if let Some(socket) = &model.qmp_socket {
+            let script = format!(
+                "{}; while kill -0 \"$MAINPID\" 2>/dev/null; do sleep 1; done",
+                powerdown_command(socket)
+            );
+            let exec_stop: Vec<String> = ["/bin/sh", "-c", &script]
+                .iter()
+                .map(|a| systemd_quote(a))
+                .collect();
+            line("ExecStop", &exec_stop.join(" "));
+        }
+        line("KillMode", "mixed");
+        line(
+            "TimeoutStopSec",
+            &format!("{}ms", self.stop_timeout.as_millis()),
+        );
+        if !model.runtime_directories.is_empty() {
+            line("RuntimeDirectory", &model.runtime_directories.join(" "));
+        }
+        if let Some(dir) = options.get_current_dir() {
+            line(
+                "WorkingDirectory",
+                &escape_specifiers(&dir.display().to_string()),
+            );
+        }
+        if let Some(umask) = options.get_umask() {
+            line("UMask", &format!("{:04o}", umask));
+        }
+        if let Some(uid) = options.get_uid() {
+            line("User", &uid.to_string());
+        }
+        if let Some(gid) = options.get_gid() {
+            line("Group", &gid.to_string());
+        }
+
+        let inherited = options
+            .get_env_inherit()
+            .iter()
+            .filter_map(|key| std::env::var(key).ok().map(|value| (key.clone(), value)));
+        let envs: Vec<(String, String)> = inherited
+            .chain(options.get_envs().iter().cloned())
+            .collect();
+        for (key, value) in envs {
+            line(
+                "Environment",
+                &environment_quote(&format!("{}={}", key, value)),
+            );
+        }
+        if !options.get_env_remove().is_empty() {
+            line("UnsetEnvironment", &options.get_env_remove().join(" "));
+        }
+        for limit in options.get_rlimits() {
+            let (_, name) = rlimit_names(limit.get_resource());
+            line(&format!("Limit{}", name), &rlimit_value(limit, "infinity"));
+        }
+        for (key, stdio) in [
+            ("StandardOutput", options.get_stdout()),
+            ("StandardError", options.get_stderr()),
+        ] {
+            match stdio {
+                QemuStdio::Null => line(key, "null"),
+                QemuStdio::File(path) => line(
+                    key,
+                    &format!("append:{}", escape_specifiers(&path.display().to_string())),
+                ),
+                QemuStdio::Pipe(Some(log)) => line(
+                    key,
+                    &format!(
+                        "append:{}",
+                        escape_specifiers(&log.get_path().display().to_string())
+                    ),
+                ),
+                QemuStdio::Inherit | QemuStdio::Pipe(None) => line(key, "journal"),
+            }
+        }
+
+        if let Some(cgroup) = options.get_cgroup() {
+            let resources = cgroup.get_resources();
+            if let Some(cpu) = resources.get_cpu_max()
+                && let Some(quota) = cpu.quota_us
+            {
+                // systemd takes at most two decimals, so round to hundredths of a percent.
+                let period = u128::from(cpu.period_us.max(1));
+                let permyriad = (u128::from(quota) * 10_000 + period / 2) / period;
+                line(
+                    "CPUQuota",
+                    &format!("{}.{:02}%", permyriad / 100, permyriad % 100),
+                );
+                line("CPUQuotaPeriodSec", &format!("{}us", cpu.period_us));
+            }
+            if let Some(bytes) = resources.get_memory_high() {
+                line("MemoryHigh", &bytes.to_string());
+            }
+            if let Some(bytes) = resources.get_memory_max() {
+                line("MemoryMax", &bytes.to_string());
+            }
+            for io in resources.get_io_max() {
+                let device = format!("/dev/block/{}:{}", io.major, io.minor);
+                for (key, limit) in [
+                    ("IOReadBandwidthMax", io.rbps),
+                    ("IOWriteBandwidthMax", io.wbps),
+                    ("IOReadIOPSMax", io.riops),
+                    ("IOWriteIOPSMax", io.wiops),
+                ] {
+                    if let Some(limit) = limit {
+                        line(key, &format!("{} {}", device, limit));
+                    }
+                }
+            }
+            if let Some(pids) = resources.get_pids_max() {
+                line("TasksMax", &pids.to_string());
+            }
+        }
+
+        let mut unit = String::new();
+        let _ = writeln!(unit, "[Unit]");
+        let _ = writeln!(unit, "Description={}", escape_specifiers(&description));
+        let _ = writeln!(unit, "After=network.target");
+        let _ = writeln!(unit, "\n[Service]");
+        for (key, value) in service {
+            let _ = writeln!(unit, "{}={}", key, value);
+        }
+        let _ = writeln!(unit, "\n[Install]");
+        let _ = writeln!(unit, "WantedBy={}", self.wanted_by);
+        Ok(unit)
+    }
+}
+
+/// systemd expands `%` specifiers in most settings.
+fn escape_specifiers(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+/// Quotes one `Environment=` assignment; unlike `ExecStart=`, `$` is not expanded there.
+fn environment_quote(assignment: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in assignment.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '%' => quoted.push_str("%%"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,167 @@
+use crate::launcher::{QemuArg, QemuLaunchArgs};
+
+/// Which template layer set an argument of a `QemuEffectiveArgs`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuArgOrigin {
+    pub layer: String,
+    /// The layer whose argument this one replaced, if it was set over an existing one.
+    pub replaced: Option<String>,
+}
+
+/// Launch args merged from a chain of profile templates, with the layer that set
+/// each argument.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuEffectiveArgs {
+    args: QemuLaunchArgs,
+    origins: Vec<QemuArgOrigin>,
+    binary_layer: String,
+    positionals_layer: String,
+    layers: Vec<String>,
+}
+
+impl QemuEffectiveArgs {
+    /// A single-layer view of `args`.
+    pub fn new(args: QemuLaunchArgs, layer: impl Into<String>) -> Self {
+        let layer = layer.into();
+        let origins = args
+            .get_args()
+            .iter()
+            .map(|_| QemuArgOrigin {
+                layer: layer.clone(),
+                replaced: None,
+            })
+            .collect();
+        Self {
+            args,
+            origins,
+            binary_layer: layer.clone(),
+            positionals_layer: layer.clone(),
+            layers: vec![layer],
+        }
+    }
+
+    pub fn get_args(&self) -> &QemuLaunchArgs {
+        &self.args
+    }
+
+    pub fn into_args(self) -> QemuLaunchArgs {
+        self.args
+    }
+
+    /// Layer names from the root template to the most derived one.
+    pub fn get_layers(&self) -> &[String] {
+        &self.layers
+    }
+
+    pub fn get_binary_layer(&self) -> &str {
+        &self.binary_layer
+    }
+
+    pub fn get_positionals_layer(&self) -> &str {
+        &self.positionals_layer
+    }
+
+    /// Each argument with the layer that set it, in command-line order.
+    pub fn iter(&self) -> impl Iterator<Item = (&QemuArg, &QemuArgOrigin)> {
+        self.args.get_args().iter().zip(&self.origins)
+    }
+
+    pub fn origin_of(&self, key: &str, id: Option<&str>) -> Option<&QemuArgOrigin> {
+        self.iter()
+            .find(|(arg, _)| arg.key_equals(key) && (id.is_none() || arg.get_id().as_deref() == id))
+            .map(|(_, origin)| origin)
+    }
+
+    pub(crate) fn push_layer(&mut self, layer: &str) {
+        self.layers.push(layer.to_string());
+    }
+
+    pub(crate) fn set_binary(&mut self, binary: &str, layer: &str) {
+        *self.args.get_mut_binary() = binary.to_string();
+        self.binary_layer = layer.to_string();
+    }
+
+    pub(crate) fn set_positionals(&mut self, positionals: &[String], layer: &str) {
+        *self.args.get_mut_positionals() = positionals.to_vec();
+        self.positionals_layer = layer.to_string();
+    }
+
+    pub(crate) fn add(&mut self, arg: QemuArg, layer: &str) {
+        self.args.get_mut_args().push(arg);
+        self.origins.push(QemuArgOrigin {
+            layer: layer.to_string(),
+            replaced: None,
+        });
+    }
+
+    /// Replaces in place the argument with the same key and id, or, if `arg` has no
+    /// id, every argument with its key. Appends `arg` if nothing matches.
+    pub(crate) fn set(&mut self, arg: QemuArg, layer: &str) {
+        let id = arg.get_id();
+        let matches: Vec<usize> = self
+            .args
+            .get_args()
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| a.key_equals(arg.key()) && (id.is_none() || a.get_id() == id))
+            .map(|(i, _)| i)
+            .collect();
+        let Some((&first, rest)) = matches.split_first() else {
+            self.add(arg, layer);
+            return;
+        };
+        for &index in rest.iter().rev() {
+            self.args.get_mut_args().remove(index);
+            self.origins.remove(index);
+        }
+        self.args.get_mut_args()[first] = arg;
+        let replaced = std::mem::replace(&mut self.origins[first].layer, layer.to_string());
+        self.origins[first].replaced = Some(replaced);
+    }
+
+    /// Removes the argument with this key and id, or every argument with the key if
+    /// `id` is `None`. Returns how many were removed.
+    pub(crate) fn remove(&mut self, key: &str, id: Option<&str>) -> usize {
+        let mut removed = 0;
+        let mut index = 0;
+        while index < self.origins.len() {
+            let arg = &self.args.get_args()[index];
+            if arg.key_equals(key) && (id.is_none() || arg.get_id().as_deref() == id) {
+                self.args.get_mut_args().remove(index);
+                self.origins.remove(index);
+                removed += 1;
+            } else {
+                index += 1;
+            }
+        }
+        removed
+    }
+}
+
+impl std::fmt::Display for QemuEffectiveArgs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self.layers.iter().map(|l| l.len()).max().unwrap_or(0);
+        writeln!(
+            f,
+            "{:width$}  {}",
+            self.binary_layer,
+            self.args.get_binary()
+        )?;
+        for (arg, origin) in self.iter() {
+            write!(f, "{:width$}  {}", origin.layer, arg.to_command_line())?;
+            if let Some(replaced) = &origin.replaced {
+                write!(f, "  (replaces {})", replaced)?;
+            }
+            writeln!(f)?;
+        }
+        if !self.args.get_positionals().is_empty() {
+            writeln!(
+                f,
+                "{:width$}  {}",
+                self.positionals_layer,
+                self.args.get_positionals().join(" ")
+            )?;
+        }
+        Ok(())
+    }
+}
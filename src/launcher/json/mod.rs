@@ -1,3 +1,17 @@
+mod effective_args;
+mod profile_format;
+mod profile_migration;
+mod profile_template;
+mod profile_vars;
+mod qemu_config_error;
+mod qemu_config_file;
 mod qemu_launch_args_json;
 
-pub use qemu_launch_args_json::QemuLaunchArgsJson;
\ No newline at end of file
+pub use effective_args::{QemuArgOrigin, QemuEffectiveArgs};
+pub use profile_format::QemuProfileFormat;
+pub use profile_migration::{PROFILE_FORMAT_VERSION, migrate_profile, profile_json_schema};
+pub use profile_template::{QemuOverlayOp, QemuProfileTemplate};
+pub use profile_vars::QemuProfileVars;
+pub use qemu_config_error::QemuConfigError;
+pub use qemu_config_file::{QemuConfigFile, QemuConfigSection, QemuConfigUnsupported};
+pub use qemu_launch_args_json::QemuLaunchArgsJson;
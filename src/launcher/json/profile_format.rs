@@ -0,0 +1,173 @@
+use std::path::Path;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::QemuConfigError;
+use super::profile_migration::{PROFILE_FORMAT_VERSION, migrate_profile, profile_format_version};
+
+/// The encodings a profile can be stored in. TOML and YAML need the `toml` and
+/// `yaml` cargo features; all three describe the same document model.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum QemuProfileFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl QemuProfileFormat {
+    /// Picks the format from the file extension (`.json`, `.toml`, `.yaml`/`.yml`).
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(QemuProfileFormat::Json),
+            "toml" => Some(QemuProfileFormat::Toml),
+            "yaml" | "yml" => Some(QemuProfileFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Like `from_path`, but JSON for unknown extensions.
+    pub(crate) fn for_path(path: &Path) -> Self {
+        Self::from_path(path).unwrap_or(QemuProfileFormat::Json)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            QemuProfileFormat::Json => true,
+            QemuProfileFormat::Toml => cfg!(feature = "toml"),
+            QemuProfileFormat::Yaml => cfg!(feature = "yaml"),
+        }
+    }
+
+    /// Serializes a document. `pretty` only affects JSON; TOML and YAML are always
+    /// written one field per line. TOML has no null, so a document holding one (e.g.
+    /// in a `QemuArg::Json` value) is rejected with the place of the null.
+    pub(crate) fn encode<T: Serialize>(
+        &self,
+        document: &T,
+        pretty: bool,
+    ) -> Result<String, QemuConfigError> {
+        match self {
+            QemuProfileFormat::Json if pretty => {
+                serde_json::to_string_pretty(document).map_err(QemuConfigError::Serialize)
+            }
+            QemuProfileFormat::Json => {
+                serde_json::to_string(document).map_err(QemuConfigError::Serialize)
+            }
+            #[cfg(feature = "toml")]
+            QemuProfileFormat::Toml => {
+                let value = serde_json::to_value(document).map_err(QemuConfigError::Serialize)?;
+                if let Some(at) = find_null(&value, String::new()) {
+                    return Err(self.error(
+                        None,
+                        format!("cannot store the null at {}; TOML has no null value", at),
+                    ));
+                }
+                toml::to_string_pretty(document).map_err(|e| self.error(None, e.to_string()))
+            }
+            #[cfg(feature = "yaml")]
+            QemuProfileFormat::Yaml => {
+                serde_yaml::to_string(document).map_err(|e| self.error(None, e.to_string()))
+            }
+            #[cfg(not(feature = "toml"))]
+            QemuProfileFormat::Toml => Err(self.disabled()),
+            #[cfg(not(feature = "yaml"))]
+            QemuProfileFormat::Yaml => Err(self.disabled()),
+        }
+    }
+
+    /// Parses a document, upgrading older format versions. Documents that are already
+    /// current are deserialized from the text itself so errors keep their position.
+    pub(crate) fn decode<T: DeserializeOwned>(&self, text: &str) -> Result<T, QemuConfigError> {
+        let document: Value = self.parse(text)?;
+        if profile_format_version(&document)? == PROFILE_FORMAT_VERSION {
+            return self.parse(text);
+        }
+        serde_json::from_value(migrate_profile(document)?)
+            .map_err(|source| QemuConfigError::Parse { path: None, source })
+    }
+
+    fn parse<T: DeserializeOwned>(&self, text: &str) -> Result<T, QemuConfigError> {
+        match self {
+            QemuProfileFormat::Json => serde_json::from_str(text)
+                .map_err(|source| QemuConfigError::Parse { path: None, source }),
+            #[cfg(feature = "toml")]
+            QemuProfileFormat::Toml => toml::from_str(text).map_err(|e| {
+                let position = e.span().map(|span| line_column(text, span.start));
+                self.error(position, e.message().to_string())
+            }),
+            #[cfg(feature = "yaml")]
+            QemuProfileFormat::Yaml => serde_yaml::from_str(text).map_err(|e| {
+                let position = e.location().map(|l| (l.line(), l.column()));
+                self.error(position, e.to_string())
+            }),
+            #[cfg(not(feature = "toml"))]
+            QemuProfileFormat::Toml => Err(self.disabled()),
+            #[cfg(not(feature = "yaml"))]
+            QemuProfileFormat::Yaml => Err(self.disabled()),
+        }
+    }
+
+    fn error(&self, position: Option<(usize, usize)>, message: String) -> QemuConfigError {
+        QemuConfigError::Format {
+            path: None,
+            format: *self,
+            line: position.map(|p| p.0),
+            column: position.map(|p| p.1),
+            message,
+        }
+    }
+
+    #[cfg(not(all(feature = "toml", feature = "yaml")))]
+    fn disabled(&self) -> QemuConfigError {
+        let feature = match self {
+            QemuProfileFormat::Toml => "toml",
+            _ => "yaml",
+        };
+        self.error(
+            None,
+            format!("support is not enabled (cargo feature `{}`)", feature),
+        )
+    }
+}
+
+impl std::fmt::Display for QemuProfileFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QemuProfileFormat::Json => write!(f, "JSON"),
+            QemuProfileFormat::Toml => write!(f, "TOML"),
+            QemuProfileFormat::Yaml => write!(f, "YAML"),
+        }
+    }
+}
+
+/// The dotted path of the first null in `value`, e.g. `qemuLaunchArgs.args[2].data.x`.
+#[cfg(feature = "toml")]
+fn find_null(value: &Value, path: String) -> Option<String> {
+    match value {
+        Value::Null => Some(path),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .find_map(|(i, item)| find_null(item, format!("{}[{}]", path, i))),
+        Value::Object(map) => map.iter().find_map(|(key, item)| {
+            let path = match path.is_empty() {
+                true => key.clone(),
+                false => format!("{}.{}", path, key),
+            };
+            find_null(item, path)
+        }),
+        Value::Bool(_) | Value::Number(_) | Value::String(_) => None,
+    }
+}
+
+/// 1-based line and column of a byte offset.
+#[cfg(feature = "toml")]
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, column)
+}
@@ -0,0 +1,73 @@
+use serde_json::Value;
+
+use super::QemuConfigError;
+
+/// The profile format written by this version of the library.
+pub const PROFILE_FORMAT_VERSION: u32 = 1;
+
+pub(crate) const FORMAT_VERSION_FIELD: &str = "formatVersion";
+
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+/// `MIGRATIONS[n]` upgrades a version `n` document to version `n + 1`.
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1];
+
+/// Version 0 is the unversioned layout, which version 1 only adds `formatVersion` to.
+fn migrate_v0_to_v1(_document: &mut Value) -> Result<(), String> {
+    Ok(())
+}
+
+/// The `formatVersion` of a profile document; 0 if absent.
+pub(crate) fn profile_format_version(document: &Value) -> Result<u32, QemuConfigError> {
+    let Some(object) = document.as_object() else {
+        return Err(QemuConfigError::Migration {
+            path: None,
+            message: "a profile must be a JSON object".to_string(),
+        });
+    };
+    match object.get(FORMAT_VERSION_FIELD) {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| QemuConfigError::Migration {
+                path: None,
+                message: format!("invalid {}: {}", FORMAT_VERSION_FIELD, version),
+            }),
+    }
+}
+
+/// Upgrades a profile document of any older format version to
+/// `PROFILE_FORMAT_VERSION`. Documents from a newer version are rejected.
+pub fn migrate_profile(mut document: Value) -> Result<Value, QemuConfigError> {
+    let mut version = profile_format_version(&document)?;
+    if version > PROFILE_FORMAT_VERSION {
+        return Err(QemuConfigError::UnsupportedVersion {
+            path: None,
+            found: version,
+            supported: PROFILE_FORMAT_VERSION,
+        });
+    }
+    while version < PROFILE_FORMAT_VERSION {
+        MIGRATIONS[version as usize](&mut document).map_err(|message| {
+            QemuConfigError::Migration {
+                path: None,
+                message: format!("upgrading from version {}: {}", version, message),
+            }
+        })?;
+        version += 1;
+        if let Some(object) = document.as_object_mut() {
+            object.insert(FORMAT_VERSION_FIELD.to_string(), Value::from(version));
+        }
+    }
+    Ok(document)
+}
+
+/// The JSON Schema (draft 2020-12) of the current profile format, as published in
+/// `schema/qemu-launch-profile.schema.json`.
+pub fn profile_json_schema() -> Value {
+    serde_json::from_str(include_str!(
+        "../../../schema/qemu-launch-profile.schema.json"
+    ))
+    .expect("the bundled profile schema is valid JSON")
+}
@@ -0,0 +1,212 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{QemuConfigError, QemuEffectiveArgs, QemuProfileFormat};
+use crate::launcher::{QemuArg, QemuLaunchArgs};
+
+/// One change a template makes to the args it inherits.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum QemuOverlayOp {
+    /// Appends the argument.
+    Add { arg: QemuArg },
+    /// Replaces the argument with the same key and id (or, for an argument without
+    /// an id, all arguments with its key) in place; appends it if there is none.
+    Set { arg: QemuArg },
+    /// Removes the argument with this key and id, or all arguments with the key if
+    /// `id` is omitted. Removing something that is not there is an error.
+    Remove {
+        key: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+    },
+}
+
+/// A profile that either defines launch args itself or `extends` another profile
+/// (a path relative to its own file) and applies an overlay to it. A plain
+/// `QemuLaunchArgsJson` document is a valid root template.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct QemuProfileTemplate {
+    #[serde(rename = "formatVersion", default)]
+    pub format_version: u32,
+    /// Label used for provenance; defaults to the file path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    #[serde(
+        rename = "qemuLaunchArgs",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub args: Option<QemuLaunchArgs>,
+    #[serde(
+        rename = "qemuBinary",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub binary: Option<String>,
+    /// Replaces the inherited positional arguments as a whole.
+    #[serde(
+        rename = "positionalArgs",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub positionals: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overlay: Vec<QemuOverlayOp>,
+}
+
+impl QemuProfileTemplate {
+    pub fn from_json_str(json: &str) -> Result<Self, QemuConfigError> {
+        Self::from_json_bytes(json.as_bytes())
+    }
+
+    pub fn from_json_bytes(bytes: &[u8]) -> Result<Self, QemuConfigError> {
+        let text = std::str::from_utf8(bytes)
+            .map_err(|e| template_error(None, format!("not UTF-8: {}", e)))?;
+        Self::from_str_as(text, QemuProfileFormat::Json)
+    }
+
+    pub fn from_str_as(text: &str, format: QemuProfileFormat) -> Result<Self, QemuConfigError> {
+        let template: Self = format.decode(text)?;
+        if template.extends.is_some() == template.args.is_some() {
+            return Err(template_error(
+                None,
+                "a template needs exactly one of `extends` and `qemuLaunchArgs`",
+            ));
+        }
+        if template.args.is_some()
+            && (template.binary.is_some()
+                || template.positionals.is_some()
+                || !template.overlay.is_empty())
+        {
+            return Err(template_error(
+                None,
+                "`qemuBinary`, `positionalArgs` and `overlay` need `extends`",
+            ));
+        }
+        Ok(template)
+    }
+
+    /// Loads the template at `path` and everything it extends, and merges them. Each
+    /// file is read in the format given by its extension, so layers can mix formats.
+    pub fn load_effective<P: AsRef<Path>>(path: P) -> Result<QemuEffectiveArgs, QemuConfigError> {
+        let mut chain = Vec::new();
+        let mut next = Some(path.as_ref().to_path_buf());
+        while let Some(path) = next {
+            let canonical =
+                std::fs::canonicalize(&path).map_err(|e| QemuConfigError::io(&path, e))?;
+            let text = std::fs::read_to_string(&path).map_err(|e| QemuConfigError::io(&path, e))?;
+            next = push_layer(&mut chain, path, canonical, &text)?;
+        }
+        merge_chain(chain)
+    }
+
+    /// Async version of `load_effective`.
+    pub async fn load_effective_async<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<QemuEffectiveArgs, QemuConfigError> {
+        let mut chain = Vec::new();
+        let mut next = Some(path.as_ref().to_path_buf());
+        while let Some(path) = next {
+            let canonical = tokio::fs::canonicalize(&path)
+                .await
+                .map_err(|e| QemuConfigError::io(&path, e))?;
+            let text = tokio::fs::read_to_string(&path)
+                .await
+                .map_err(|e| QemuConfigError::io(&path, e))?;
+            next = push_layer(&mut chain, path, canonical, &text)?;
+        }
+        merge_chain(chain)
+    }
+
+    /// Applies this template's binary, positionals and overlay on top of `base`,
+    /// recording `layer` as their source.
+    pub fn apply(&self, base: &mut QemuEffectiveArgs, layer: &str) -> Result<(), QemuConfigError> {
+        base.push_layer(layer);
+        if let Some(binary) = &self.binary {
+            base.set_binary(binary, layer);
+        }
+        if let Some(positionals) = &self.positionals {
+            base.set_positionals(positionals, layer);
+        }
+        for op in &self.overlay {
+            match op {
+                QemuOverlayOp::Add { arg } => base.add(arg.clone(), layer),
+                QemuOverlayOp::Set { arg } => base.set(arg.clone(), layer),
+                QemuOverlayOp::Remove { key, id } => {
+                    if base.remove(key, id.as_deref()) == 0 {
+                        let what = match id {
+                            Some(id) => format!("{} with id {}", key, id),
+                            None => key.clone(),
+                        };
+                        return Err(template_error(
+                            None,
+                            format!("overlay removes {}, which is not inherited", what),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn template_error(path: Option<&Path>, message: impl Into<String>) -> QemuConfigError {
+    QemuConfigError::Template {
+        path: path.map(Path::to_path_buf),
+        message: message.into(),
+    }
+}
+
+/// Parses one template of an `extends` chain and returns the path of its parent.
+fn push_layer(
+    chain: &mut Vec<(PathBuf, PathBuf, QemuProfileTemplate)>,
+    path: PathBuf,
+    canonical: PathBuf,
+    text: &str,
+) -> Result<Option<PathBuf>, QemuConfigError> {
+    if chain.iter().any(|(_, c, _)| *c == canonical) {
+        let mut cycle: Vec<String> = chain
+            .iter()
+            .map(|(p, _, _)| p.display().to_string())
+            .collect();
+        cycle.push(path.display().to_string());
+        return Err(template_error(
+            Some(&path),
+            format!("`extends` cycle: {}", cycle.join(" -> ")),
+        ));
+    }
+    let template = QemuProfileTemplate::from_str_as(text, QemuProfileFormat::for_path(&path))
+        .map_err(|e| e.with_path(&path))?;
+    let parent = template.extends.as_ref().map(|extends| {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        dir.join(extends)
+    });
+    chain.push((path, canonical, template));
+    Ok(parent)
+}
+
+/// Merges a chain collected from the most derived template to the root.
+fn merge_chain(
+    chain: Vec<(PathBuf, PathBuf, QemuProfileTemplate)>,
+) -> Result<QemuEffectiveArgs, QemuConfigError> {
+    let mut layers = chain.into_iter().rev().map(|(path, _, template)| {
+        let layer = template
+            .name
+            .clone()
+            .unwrap_or_else(|| path.display().to_string());
+        (path, layer, template)
+    });
+    let (_, root_layer, root) = layers.next().expect("an extends chain is never empty");
+    let args = root.args.expect("checked when parsing");
+    let mut effective = QemuEffectiveArgs::new(args, root_layer);
+    for (path, layer, template) in layers {
+        template
+            .apply(&mut effective, &layer)
+            .map_err(|e| e.with_path(&path))?;
+    }
+    Ok(effective)
+}
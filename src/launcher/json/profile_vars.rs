@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use super::QemuConfigError;
+use crate::launcher::qemu_args::{QemuOptionArity, qemu_option_arity};
+use crate::launcher::{QemuArg, QemuLaunchArgs};
+
+const ALLOC_PREFIX: &str = "alloc.";
+const ENV_PREFIX: &str = "env:";
+
+/// Values for `${...}` references in launch args.
+///
+/// * `${name}` looks up a variable set with `with_var` (`runtime_dir` is predefined
+///   as `$XDG_RUNTIME_DIR/qemu-lite-wrapper`, or under the system temp directory).
+/// * `${env:NAME}` reads an environment variable.
+/// * `${alloc.port}`, `${alloc.mac}` and `${alloc.uuid}` allocate a free TCP port, a
+///   random locally administered MAC address and a random UUID. A suffix names
+///   separate allocations (`${alloc.port.vnc}`, `${alloc.port.ssh}`); the same
+///   reference always resolves to the same value for one `QemuProfileVars`.
+/// * `${name:-default}` falls back to `default` when `name` is undefined; `default`
+///   may contain references itself (`${a:-${b}}`).
+/// * `$$` is a literal `$`.
+///
+/// In the values of `key=value,...` options, commas in substituted values are
+/// escaped as `,,`, so a value cannot add properties of its own.
+///
+/// Use one `QemuProfileVars` per VM instance so allocations are not shared.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuProfileVars {
+    vars: BTreeMap<String, String>,
+    allocated: BTreeMap<String, String>,
+}
+
+impl Default for QemuProfileVars {
+    fn default() -> Self {
+        let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir)
+            .join("qemu-lite-wrapper");
+        Self {
+            vars: BTreeMap::from([(
+                "runtime_dir".to_string(),
+                runtime_dir.to_string_lossy().into_owned(),
+            )]),
+            allocated: BTreeMap::new(),
+        }
+    }
+}
+
+impl QemuProfileVars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set_var(name, value);
+        self
+    }
+
+    pub fn set_var(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.vars.insert(name.into(), value.into());
+    }
+
+    pub fn get_var(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(String::as_str)
+    }
+
+    /// Values allocated so far, keyed by reference (e.g. `alloc.port.vnc`).
+    pub fn get_allocated(&self) -> &BTreeMap<String, String> {
+        &self.allocated
+    }
+
+    /// Substitutes every reference in `text`.
+    pub fn substitute(&mut self, text: &str) -> Result<String, QemuConfigError> {
+        self.substitute_escaped(text, false)
+    }
+
+    /// `substitute`, escaping commas in the substituted values when `opts` is set.
+    fn substitute_escaped(&mut self, text: &str, opts: bool) -> Result<String, QemuConfigError> {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(pos) = rest.find('$') {
+            out.push_str(&rest[..pos]);
+            rest = &rest[pos + 1..];
+            if let Some(after) = rest.strip_prefix('$') {
+                out.push('$');
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('{') {
+                let Some(end) = closing_brace(after) else {
+                    return Err(var_error(format!("unterminated `${{` in {:?}", text)));
+                };
+                out.push_str(&self.lookup(&after[..end], opts)?);
+                rest = &after[end + 1..];
+            } else {
+                out.push('$');
+            }
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// A copy of `args` with the binary, every argument value and the positionals
+    /// substituted. Option names are left alone.
+    pub fn resolve(&mut self, args: &QemuLaunchArgs) -> Result<QemuLaunchArgs, QemuConfigError> {
+        let mut resolved = args.clone();
+        let binary = self.substitute(args.get_binary())?;
+        *resolved.get_mut_binary() = binary;
+        for arg in resolved.get_mut_args() {
+            let key = arg.key().to_string();
+            let opts = qemu_option_arity(&key) == Some(QemuOptionArity::Opts);
+            let context = |e: QemuConfigError| match e {
+                QemuConfigError::Variable { path, message } => QemuConfigError::Variable {
+                    path,
+                    message: format!("{} (in {})", message, key),
+                },
+                e => e,
+            };
+            match arg {
+                QemuArg::Flag(_) => {}
+                QemuArg::KeyValue(_, value) => {
+                    // A JSON value is not split on commas.
+                    let opts = opts && !value.trim_start().starts_with('{');
+                    *value = self.substitute_escaped(value, opts).map_err(context)?;
+                }
+                QemuArg::List(_, items) => {
+                    for item in items {
+                        *item = self.substitute_escaped(item, opts).map_err(context)?;
+                    }
+                }
+                QemuArg::Json(_, value) => self.substitute_json(value).map_err(context)?,
+            }
+        }
+        for positional in resolved.get_mut_positionals() {
+            *positional = self.substitute(positional)?;
+        }
+        Ok(resolved)
+    }
+
+    fn substitute_json(&mut self, value: &mut Value) -> Result<(), QemuConfigError> {
+        match value {
+            Value::String(s) => *s = self.substitute(s)?,
+            Value::Array(items) => {
+                for item in items {
+                    self.substitute_json(item)?;
+                }
+            }
+            Value::Object(map) => {
+                for item in map.values_mut() {
+                    self.substitute_json(item)?;
+                }
+            }
+            Value::Null | Value::Bool(_) | Value::Number(_) => {}
+        }
+        Ok(())
+    }
+
+    /// The text a reference is replaced with. With `opts`, a value has its commas
+    /// escaped; the literal text of a default is used as written, as it already is.
+    fn lookup(&mut self, reference: &str, opts: bool) -> Result<String, QemuConfigError> {
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        let value = if let Some(var) = name.strip_prefix(ENV_PREFIX) {
+            std::env::var(var).ok()
+        } else if name.starts_with(ALLOC_PREFIX) {
+            Some(self.allocate(name)?)
+        } else {
+            self.vars.get(name).cloned()
+        };
+        match (value, default) {
+            (Some(value), _) if opts => Ok(value.replace(',', ",,")),
+            (Some(value), _) => Ok(value),
+            (None, Some(default)) => self.substitute_escaped(default, opts),
+            (None, None) => Err(var_error(format!("undefined variable ${{{}}}", name))),
+        }
+    }
+
+    fn allocate(&mut self, name: &str) -> Result<String, QemuConfigError> {
+        if let Some(value) = self.allocated.get(name) {
+            return Ok(value.clone());
+        }
+        let kind = name[ALLOC_PREFIX.len()..].split('.').next().unwrap_or("");
+        let value = match kind {
+            "port" => std::net::TcpListener::bind(("127.0.0.1", 0))
+                .and_then(|l| l.local_addr())
+                .map(|a| a.port().to_string())
+                .map_err(|e| var_error(format!("cannot allocate ${{{}}}: {}", name, e)))?,
+            "mac" => {
+                let b = random_bytes::<5>().map_err(|e| var_error(e.to_string()))?;
+                // 0x52 (as in QEMU's default 52:54:00 prefix) is locally administered and unicast.
+                format!(
+                    "52:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+                    b[0], b[1], b[2], b[3], b[4]
+                )
+            }
+            "uuid" => {
+                let mut b = random_bytes::<16>().map_err(|e| var_error(e.to_string()))?;
+                b[6] = (b[6] & 0x0f) | 0x40;
+                b[8] = (b[8] & 0x3f) | 0x80;
+                let hex: String = b.iter().map(|x| format!("{:02x}", x)).collect();
+                format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..]
+                )
+            }
+            _ => {
+                return Err(var_error(format!(
+                    "unknown allocation ${{{}}} (expected alloc.port, alloc.mac or alloc.uuid)",
+                    name
+                )));
+            }
+        };
+        self.allocated.insert(name.to_string(), value.clone());
+        Ok(value)
+    }
+}
+
+/// The index of the `}` that closes a reference, skipping `$$` and nested `${...}`.
+fn closing_brace(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'$' if matches!(bytes.get(i + 1), Some(b'$' | b'{')) => {
+                if bytes[i + 1] == b'{' {
+                    depth += 1;
+                }
+                i += 2;
+                continue;
+            }
+            b'}' if depth == 0 => return Some(i),
+            b'}' => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+fn var_error(message: String) -> QemuConfigError {
+    QemuConfigError::Variable {
+        path: None,
+        message,
+    }
+}
+
+fn random_bytes<const N: usize>() -> std::io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
@@ -0,0 +1,202 @@
+use std::path::{Path, PathBuf};
+
+use super::QemuProfileFormat;
+
+#[derive(Debug)]
+pub enum QemuConfigError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// Malformed or invalid profile content. `path` is `None` when parsing a string.
+    Parse {
+        path: Option<PathBuf>,
+        source: serde_json::Error,
+    },
+    Serialize(serde_json::Error),
+    /// The profile was written by a newer version of the format.
+    UnsupportedVersion {
+        path: Option<PathBuf>,
+        found: u32,
+        supported: u32,
+    },
+    Migration {
+        path: Option<PathBuf>,
+        message: String,
+    },
+    /// An invalid template, `extends` chain or overlay.
+    Template {
+        path: Option<PathBuf>,
+        message: String,
+    },
+    /// Malformed TOML or YAML, or a format whose cargo feature is not enabled.
+    /// (JSON parse errors are `Parse`.)
+    Format {
+        path: Option<PathBuf>,
+        format: QemuProfileFormat,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+    /// An undefined or malformed `${...}` reference.
+    Variable {
+        path: Option<PathBuf>,
+        message: String,
+    },
+    /// A malformed QEMU `-readconfig` file.
+    ReadConfig {
+        path: Option<PathBuf>,
+        line: usize,
+        message: String,
+    },
+}
+
+impl QemuConfigError {
+    pub(crate) fn io(path: impl AsRef<Path>, source: std::io::Error) -> Self {
+        QemuConfigError::Io {
+            path: path.as_ref().to_path_buf(),
+            source,
+        }
+    }
+
+    /// Records the file a path-less error came from.
+    pub(crate) fn with_path(mut self, file: impl AsRef<Path>) -> Self {
+        match &mut self {
+            QemuConfigError::Parse { path, .. }
+            | QemuConfigError::UnsupportedVersion { path, .. }
+            | QemuConfigError::Migration { path, .. }
+            | QemuConfigError::Template { path, .. }
+            | QemuConfigError::Format { path, .. }
+            | QemuConfigError::Variable { path, .. }
+            | QemuConfigError::ReadConfig { path, .. } => {
+                path.get_or_insert_with(|| file.as_ref().to_path_buf());
+            }
+            QemuConfigError::Io { .. } | QemuConfigError::Serialize(_) => {}
+        }
+        self
+    }
+
+    /// The file the error relates to, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            QemuConfigError::Io { path, .. } => Some(path),
+            QemuConfigError::Parse { path, .. }
+            | QemuConfigError::UnsupportedVersion { path, .. }
+            | QemuConfigError::Migration { path, .. }
+            | QemuConfigError::Template { path, .. }
+            | QemuConfigError::Format { path, .. }
+            | QemuConfigError::Variable { path, .. }
+            | QemuConfigError::ReadConfig { path, .. } => path.as_deref(),
+            QemuConfigError::Serialize(_) => None,
+        }
+    }
+
+    /// 1-based line of a parse error.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            QemuConfigError::Parse { source, .. } if source.line() > 0 => Some(source.line()),
+            QemuConfigError::Format { line, .. } => *line,
+            QemuConfigError::ReadConfig { line, .. } => Some(*line),
+            _ => None,
+        }
+    }
+
+    /// Column of a parse error; 0 when it is at the very start of a line, e.g. at EOF.
+    pub fn column(&self) -> Option<usize> {
+        match self {
+            QemuConfigError::Parse { source, .. } if source.line() > 0 => Some(source.column()),
+            QemuConfigError::Format { column, .. } => *column,
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for QemuConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QemuConfigError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            QemuConfigError::Parse {
+                path: Some(path),
+                source,
+            } => write!(f, "{}: invalid profile: {}", path.display(), source),
+            QemuConfigError::Parse { path: None, source } => {
+                write!(f, "Invalid profile: {}", source)
+            }
+            QemuConfigError::Serialize(e) => write!(f, "Cannot serialize profile: {}", e),
+            QemuConfigError::UnsupportedVersion {
+                path,
+                found,
+                supported,
+            } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(
+                    f,
+                    "Profile format version {} is newer than the supported version {}",
+                    found, supported
+                )
+            }
+            QemuConfigError::Migration { path, message } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(f, "Cannot migrate profile: {}", message)
+            }
+            QemuConfigError::Template { path, message } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(f, "Invalid template: {}", message)
+            }
+            QemuConfigError::Format {
+                path,
+                format,
+                line,
+                column,
+                message,
+            } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(f, "Invalid {} profile: {}", format, message)?;
+                if let (Some(line), Some(column)) = (line, column) {
+                    write!(f, " at line {} column {}", line, column)?;
+                }
+                Ok(())
+            }
+            QemuConfigError::Variable { path, message } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(f, "Cannot substitute variables: {}", message)
+            }
+            QemuConfigError::ReadConfig {
+                path,
+                line,
+                message,
+            } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(f, "Invalid QEMU config file: {} at line {}", message, line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for QemuConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QemuConfigError::Io { source, .. } => Some(source),
+            QemuConfigError::Parse { source, .. } => Some(source),
+            QemuConfigError::Serialize(e) => Some(e),
+            QemuConfigError::UnsupportedVersion { .. }
+            | QemuConfigError::Migration { .. }
+            | QemuConfigError::Template { .. }
+            | QemuConfigError::Format { .. }
+            | QemuConfigError::Variable { .. }
+            | QemuConfigError::ReadConfig { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,390 @@
+use std::path::Path;
+
+use super::QemuConfigError;
+use crate::launcher::qemu_args::canonical_option_name;
+use crate::launcher::{QemuArg, QemuLaunchArgs};
+
+/// Config file groups, the option each one stands for, and the property a leading
+/// bare value of that option is short for.
+const GROUPS: &[(&str, &str, Option<&str>)] = &[
+    ("drive", "-drive", None),
+    ("device", "-device", Some("driver")),
+    ("chardev", "-chardev", Some("backend")),
+    ("netdev", "-netdev", Some("type")),
+    ("object", "-object", Some("qom-type")),
+    ("mon", "-mon", Some("chardev")),
+    ("fsdev", "-fsdev", Some("fsdriver")),
+    ("tpmdev", "-tpmdev", Some("type")),
+    ("audiodev", "-audiodev", Some("driver")),
+    ("numa", "-numa", Some("type")),
+    ("machine", "-machine", Some("type")),
+    ("accel", "-accel", Some("accel")),
+    ("memory", "-m", Some("size")),
+    ("smp-opts", "-smp", Some("cpus")),
+    ("boot-opts", "-boot", Some("order")),
+    ("name", "-name", Some("guest")),
+    ("rtc", "-rtc", None),
+    ("icount", "-icount", Some("shift")),
+    ("spice", "-spice", None),
+    ("sandbox", "-sandbox", Some("enable")),
+    ("overcommit", "-overcommit", None),
+    ("msg", "-msg", None),
+    ("fw_cfg", "-fw_cfg", Some("name")),
+    ("iscsi", "-iscsi", None),
+    ("action", "-action", None),
+    ("global", "-global", None),
+];
+
+/// Single-value options that are shorthands for a `-machine` property.
+const MACHINE_PROPERTIES: &[(&str, &str)] = &[
+    ("-kernel", "kernel"),
+    ("-initrd", "initrd"),
+    ("-append", "append"),
+    ("-dtb", "dtb"),
+    ("-bios", "firmware"),
+];
+
+/// QEMU reads names and values with fixed-size buffers.
+const MAX_NAME_LEN: usize = 63;
+const MAX_VALUE_LEN: usize = 1023;
+
+/// One `[group "id"]` section of a QEMU config file.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuConfigSection {
+    group: String,
+    id: Option<String>,
+    properties: Vec<(String, String)>,
+}
+
+impl QemuConfigSection {
+    pub fn get_group(&self) -> &str {
+        &self.group
+    }
+
+    pub fn get_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn get_properties(&self) -> &[(String, String)] {
+        &self.properties
+    }
+
+    pub fn get_property(&self, name: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The equivalent command-line argument, e.g. `-device virtio-net-pci,id=n0,...`.
+    pub fn to_arg(&self) -> QemuArg {
+        let (_, option, implied) = group_entry(&self.group).expect("checked when parsing");
+        let mut items = Vec::new();
+        let mut bare = None;
+        if let Some(implied) = implied
+            && let Some(value) = self.get_property(implied)
+            && !value.contains('=')
+        {
+            items.push(value.replace(',', ",,"));
+            bare = Some(implied);
+        }
+        if let Some(id) = &self.id {
+            items.push(format!("id={}", id.replace(',', ",,")));
+        }
+        let mut skipped = false;
+        for (key, value) in &self.properties {
+            if Some(key.as_str()) == bare.copied() && !skipped {
+                skipped = true;
+                continue;
+            }
+            items.push(format!("{}={}", key, value.replace(',', ",,")));
+        }
+        if items.len() == 1 {
+            QemuArg::from_key_value(*option, items.remove(0))
+        } else {
+            QemuArg::List(option.to_string(), items)
+        }
+    }
+}
+
+/// An argument (or positional) that `QemuConfigFile::from_launch_args` left out.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuConfigUnsupported {
+    tokens: Vec<String>,
+    reason: String,
+}
+
+impl QemuConfigUnsupported {
+    /// The argument as it appears on the command line.
+    pub fn get_tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    pub fn get_reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl std::fmt::Display for QemuConfigUnsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.tokens.join(" "), self.reason)
+    }
+}
+
+/// A QEMU config file as read by `-readconfig` and written by `-writeconfig`: INI-like
+/// `[group]` or `[group "id"]` sections of `key = "value"` lines.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub struct QemuConfigFile {
+    sections: Vec<QemuConfigSection>,
+}
+
+impl QemuConfigFile {
+    pub fn parse(text: &str) -> Result<Self, QemuConfigError> {
+        let mut sections: Vec<QemuConfigSection> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| QemuConfigError::ReadConfig {
+                path: None,
+                line: index + 1,
+                message,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                let header = header
+                    .strip_suffix(']')
+                    .ok_or_else(|| error("unterminated section header".to_string()))?;
+                let (group, id) = match header.split_once(char::is_whitespace) {
+                    Some((group, id)) => {
+                        let id = id
+                            .trim()
+                            .strip_prefix('"')
+                            .and_then(|id| id.strip_suffix('"'))
+                            .filter(|id| !id.contains('"'))
+                            .ok_or_else(|| {
+                                error(format!("malformed section id in [{}]", header))
+                            })?;
+                        (group, Some(id.to_string()))
+                    }
+                    None => (header, None),
+                };
+                if group_entry(group).is_none() {
+                    return Err(error(format!("unknown group [{}]", group)));
+                }
+                sections.push(QemuConfigSection {
+                    group: group.to_string(),
+                    id,
+                    properties: Vec::new(),
+                });
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("expected `key = \"value\"`, found `{}`", line)))?;
+            let key = key.trim();
+            let value = value
+                .trim()
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .filter(|value| !value.contains('"'))
+                .ok_or_else(|| error(format!("value of {} must be a quoted string", key)))?;
+            if key.is_empty() || key.contains(char::is_whitespace) {
+                return Err(error(format!("invalid property name `{}`", key)));
+            }
+            let section = sections
+                .last_mut()
+                .ok_or_else(|| error(format!("property {} outside of a section", key)))?;
+            section
+                .properties
+                .push((key.to_string(), value.to_string()));
+        }
+        Ok(Self { sections })
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, QemuConfigError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| QemuConfigError::io(path, e))?;
+        Self::parse(&text).map_err(|e| e.with_path(path))
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), QemuConfigError> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_string()).map_err(|e| QemuConfigError::io(path, e))
+    }
+
+    /// Converts the args that have a config file equivalent and returns the rest,
+    /// with the reason, in command-line order. The binary is never part of the file.
+    pub fn from_launch_args(args: &QemuLaunchArgs) -> (Self, Vec<QemuConfigUnsupported>) {
+        let mut sections = Vec::new();
+        let mut unsupported = Vec::new();
+        for arg in args.get_args() {
+            match section_from_arg(arg) {
+                Ok(section) => sections.push(section),
+                Err(reason) => unsupported.push(QemuConfigUnsupported {
+                    tokens: arg.to_args(),
+                    reason,
+                }),
+            }
+        }
+        for positional in args.get_positionals() {
+            unsupported.push(QemuConfigUnsupported {
+                tokens: vec![positional.clone()],
+                reason: "positional disk images have no config file equivalent; use -drive"
+                    .to_string(),
+            });
+        }
+        (Self { sections }, unsupported)
+    }
+
+    pub fn get_sections(&self) -> &[QemuConfigSection] {
+        &self.sections
+    }
+
+    /// The command-line arguments equivalent to the whole file.
+    pub fn to_args(&self) -> Vec<QemuArg> {
+        self.sections
+            .iter()
+            .map(QemuConfigSection::to_arg)
+            .collect()
+    }
+
+    /// Replaces every `-readconfig <file>` argument with the arguments from that file,
+    /// in place. Relative paths are resolved against the current directory, as QEMU does.
+    pub fn inline_readconfig(args: &QemuLaunchArgs) -> Result<QemuLaunchArgs, QemuConfigError> {
+        let mut inlined = args.clone();
+        let mut result = Vec::new();
+        for arg in args.get_args() {
+            match arg {
+                QemuArg::KeyValue(key, path) if canonical_option_name(key) == "-readconfig" => {
+                    result.extend(Self::load_from_file(path)?.to_args());
+                }
+                _ => result.push(arg.clone()),
+            }
+        }
+        *inlined.get_mut_args() = result;
+        Ok(inlined)
+    }
+}
+
+impl std::fmt::Display for QemuConfigFile {
+    /// Writes the file in the layout of QEMU's `-writeconfig`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "# qemu config file")?;
+        writeln!(f)?;
+        for section in &self.sections {
+            match &section.id {
+                Some(id) => writeln!(f, "[{} \"{}\"]", section.group, id)?,
+                None => writeln!(f, "[{}]", section.group)?,
+            }
+            for (key, value) in &section.properties {
+                writeln!(f, "  {} = \"{}\"", key, value)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn group_entry(group: &str) -> Option<&'static (&'static str, &'static str, Option<&'static str>)> {
+    GROUPS.iter().find(|(name, _, _)| *name == group)
+}
+
+fn section_from_arg(arg: &QemuArg) -> Result<QemuConfigSection, String> {
+    let option = canonical_option_name(arg.key());
+    if option == "-enable-kvm" {
+        return section(
+            "accel",
+            None,
+            vec![("accel".to_string(), "kvm".to_string())],
+        );
+    }
+    if let Some((_, property)) = MACHINE_PROPERTIES.iter().find(|(o, _)| *o == option)
+        && let QemuArg::KeyValue(_, value) = arg
+    {
+        return section("machine", None, vec![(property.to_string(), value.clone())]);
+    }
+    let Some((group, _, implied)) = GROUPS.iter().find(|(_, o, _)| *o == option) else {
+        return Err("no config file equivalent".to_string());
+    };
+    let items = match arg.to_opts_syntax() {
+        Some(QemuArg::KeyValue(_, value)) => vec![value],
+        Some(QemuArg::List(_, list)) => list,
+        Some(_) => return Err("no config file equivalent".to_string()),
+        None => return Err("the JSON argument has no key=value form".to_string()),
+    };
+
+    // `-global driver.property=value`
+    if option == "-global"
+        && let [item] = items.as_slice()
+        && let Some((name, value)) = item.split_once('=')
+        && let Some((driver, property)) = name.split_once('.')
+    {
+        let properties = vec![
+            ("driver".to_string(), driver.to_string()),
+            ("property".to_string(), property.to_string()),
+            ("value".to_string(), value.replace(",,", ",")),
+        ];
+        return section(group, None, properties);
+    }
+
+    let mut id = None;
+    let mut properties = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let (key, value) = match item.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.replace(",,", ",")),
+            None => match implied {
+                Some(implied) if index == 0 => (implied.to_string(), item.replace(",,", ",")),
+                _ if item.starts_with("no") => {
+                    return Err(format!(
+                        "bare property `{}` is ambiguous; write it as key=value",
+                        item
+                    ));
+                }
+                _ => (item.clone(), "on".to_string()),
+            },
+        };
+        if key == "id" {
+            id = Some(value);
+        } else {
+            properties.push((key, value));
+        }
+    }
+    section(group, id, properties)
+}
+
+fn section(
+    group: &str,
+    id: Option<String>,
+    properties: Vec<(String, String)>,
+) -> Result<QemuConfigSection, String> {
+    if let Some(id) = &id
+        && (id.len() > MAX_NAME_LEN || id.contains(['"', '\n']))
+    {
+        return Err(format!(
+            "the id `{}` cannot be written to a config file",
+            id
+        ));
+    }
+    for (key, _) in &properties {
+        if key.is_empty()
+            || key.len() > MAX_NAME_LEN
+            || key.contains(|c: char| c.is_whitespace() || c == '"' || c == '=')
+        {
+            return Err(format!("`{}` cannot be written to a config file", key));
+        }
+    }
+    for (key, value) in &properties {
+        if value.len() > MAX_VALUE_LEN || value.contains(['"', '\n']) {
+            return Err(format!(
+                "the value of {} cannot be written to a config file",
+                key
+            ));
+        }
+    }
+    Ok(QemuConfigSection {
+        group: group.to_string(),
+        id,
+        properties,
+    })
+}
@@ -1,17 +1,26 @@
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use serde::{Deserialize, Serialize};
 
+use super::{PROFILE_FORMAT_VERSION, QemuConfigError, QemuProfileFormat};
 use crate::launcher::QemuLaunchArgs;
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct QemuLaunchArgsJson {
+    /// Missing in documents written before the format was versioned (version 0).
+    #[serde(rename = "formatVersion", default)]
+    pub format_version: u32,
     #[serde(rename = "qemuLaunchArgs")]
     pub args: QemuLaunchArgs,
 }
 impl QemuLaunchArgsJson {
     pub fn new(args: QemuLaunchArgs) -> Self {
-        Self { args: args }
+        Self {
+            format_version: PROFILE_FORMAT_VERSION,
+            args,
+        }
     }
 
     pub fn to_json_string(&self) -> Result<String, serde_json::Error> {
@@ -30,25 +39,144 @@ impl QemuLaunchArgsJson {
         serde_json::to_vec_pretty(self)
     }
 
-    pub fn from_json_str(json: &str) -> Result<Self, serde_json::Error> {
-        serde_json::from_str(json)
+    /// Parses a profile, upgrading documents of older format versions.
+    pub fn from_json_str(json: &str) -> Result<Self, QemuConfigError> {
+        Self::from_json_bytes(json.as_bytes())
     }
 
-    pub fn from_json_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
-        serde_json::from_slice(bytes)
+    pub fn from_json_bytes(bytes: &[u8]) -> Result<Self, QemuConfigError> {
+        Self::from_bytes_as(bytes, QemuProfileFormat::Json)
     }
 
-    pub fn save_to_file<P: AsRef<Path>>(&self, path: P, pretty: bool) -> std::io::Result<()> {
-        let content = if pretty {
-            self.to_json_string_pretty().unwrap()
-        } else {
-            self.to_json_string().unwrap()
-        };
-        std::fs::write(path, content)
+    /// Parses a profile in any of the supported formats, upgrading older versions.
+    pub fn from_str_as(text: &str, format: QemuProfileFormat) -> Result<Self, QemuConfigError> {
+        format.decode(text)
     }
 
-    pub fn load_from_file<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        Ok(serde_json::from_str(&content).unwrap())
+    /// Serializes the profile; `pretty` only affects JSON.
+    pub fn to_string_as(
+        &self,
+        format: QemuProfileFormat,
+        pretty: bool,
+    ) -> Result<String, QemuConfigError> {
+        format.encode(self, pretty)
     }
+
+    fn from_bytes_as(bytes: &[u8], format: QemuProfileFormat) -> Result<Self, QemuConfigError> {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => format.decode(text),
+            Err(_) if format == QemuProfileFormat::Json => serde_json::from_slice(bytes)
+                .map_err(|source| QemuConfigError::Parse { path: None, source }),
+            Err(e) => Err(QemuConfigError::Format {
+                path: None,
+                format,
+                line: None,
+                column: None,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    /// Writes the profile to a temporary file next to `path` and renames it into
+    /// place, so readers never see a partially written file. An existing file keeps
+    /// its permissions, and the directory is synced so the rename survives a crash.
+    /// The format follows the extension of `path` (see `QemuProfileFormat::from_path`),
+    /// defaulting to JSON.
+    pub fn save_to_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        pretty: bool,
+    ) -> Result<(), QemuConfigError> {
+        let path = path.as_ref();
+        let content = self.to_bytes(path, pretty).map_err(|e| e.with_path(path))?;
+        let tmp = temp_path(path);
+        let result = (|| {
+            let mut file = std::fs::File::create(&tmp)?;
+            if let Ok(metadata) = std::fs::metadata(path) {
+                file.set_permissions(metadata.permissions())?;
+            }
+            file.write_all(&content)?;
+            file.sync_all()?;
+            std::fs::rename(&tmp, path)?;
+            std::fs::File::open(parent_dir(path))?.sync_all()
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        result.map_err(|e| QemuConfigError::io(path, e))
+    }
+
+    /// Reads a profile in the format given by the extension of `path`.
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, QemuConfigError> {
+        let path = path.as_ref();
+        let content = std::fs::read(path).map_err(|e| QemuConfigError::io(path, e))?;
+        Self::from_bytes_as(&content, QemuProfileFormat::for_path(path))
+            .map_err(|e| e.with_path(path))
+    }
+
+    /// Async version of `save_to_file`.
+    pub async fn save_to_file_async<P: AsRef<Path>>(
+        &self,
+        path: P,
+        pretty: bool,
+    ) -> Result<(), QemuConfigError> {
+        let path = path.as_ref();
+        let content = self.to_bytes(path, pretty).map_err(|e| e.with_path(path))?;
+        let tmp = temp_path(path);
+        let result = async {
+            let mut file = tokio::fs::File::create(&tmp).await?;
+            if let Ok(metadata) = tokio::fs::metadata(path).await {
+                file.set_permissions(metadata.permissions()).await?;
+            }
+            tokio::io::AsyncWriteExt::write_all(&mut file, &content).await?;
+            file.sync_all().await?;
+            tokio::fs::rename(&tmp, path).await?;
+            tokio::fs::File::open(parent_dir(path))
+                .await?
+                .sync_all()
+                .await
+        }
+        .await;
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&tmp).await;
+        }
+        result.map_err(|e| QemuConfigError::io(path, e))
+    }
+
+    pub async fn load_from_file_async<P: AsRef<Path>>(path: P) -> Result<Self, QemuConfigError> {
+        let path = path.as_ref();
+        let content = tokio::fs::read(path)
+            .await
+            .map_err(|e| QemuConfigError::io(path, e))?;
+        Self::from_bytes_as(&content, QemuProfileFormat::for_path(path))
+            .map_err(|e| e.with_path(path))
+    }
+
+    fn to_bytes(&self, path: &Path, pretty: bool) -> Result<Vec<u8>, QemuConfigError> {
+        let content = QemuProfileFormat::for_path(path).encode(self, pretty)?;
+        Ok(content.into_bytes())
+    }
+}
+
+/// The directory holding `path`, whose entry the rename changes.
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// A unique sibling of `path`, so the final rename stays on one filesystem.
+fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
 }
@@ -0,0 +1,239 @@
+use std::path::{Path, PathBuf};
+
+use super::{
+    QemuArg, QemuCgroupConfig, QemuPassedFd, QemuProcessGroup, QemuRlimit, QemuStdio,
+    RotatingLogConfig,
+};
+
+/// How `QemuProcess` spawns QEMU, beyond the argv in `QemuLaunchArgs`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuLaunchOptions {
+    stdout: QemuStdio,
+    stderr: QemuStdio,
+    output_buffer_lines: usize,
+    env_clear: bool,
+    env_inherit: Vec<String>,
+    envs: Vec<(String, String)>,
+    env_remove: Vec<String>,
+    current_dir: Option<PathBuf>,
+    umask: Option<u32>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    process_group: QemuProcessGroup,
+    parent_death_signal: Option<i32>,
+    passed_fds: Vec<QemuPassedFd>,
+    rlimits: Vec<QemuRlimit>,
+    cgroup: Option<QemuCgroupConfig>,
+}
+
+impl Default for QemuLaunchOptions {
+    fn default() -> Self {
+        Self {
+            stdout: QemuStdio::Inherit,
+            stderr: QemuStdio::Inherit,
+            output_buffer_lines: 1000,
+            env_clear: false,
+            env_inherit: Vec::new(),
+            envs: Vec::new(),
+            env_remove: Vec::new(),
+            current_dir: None,
+            umask: None,
+            uid: None,
+            gid: None,
+            process_group: QemuProcessGroup::Inherit,
+            parent_death_signal: None,
+            passed_fds: Vec::new(),
+            rlimits: Vec::new(),
+            cgroup: None,
+        }
+    }
+}
+
+impl QemuLaunchOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pipes both streams into the output buffer.
+    pub fn captured() -> Self {
+        Self::default()
+            .with_stdout(QemuStdio::piped())
+            .with_stderr(QemuStdio::piped())
+    }
+
+    /// Pipes both streams into the output buffer and mirrors them to rotating logs
+    /// named `<prefix>.stdout.log` and `<prefix>.stderr.log`.
+    pub fn captured_with_logs(prefix: impl AsRef<str>, max_bytes: u64, max_files: usize) -> Self {
+        let log = |name: &str| {
+            RotatingLogConfig::new(format!("{}.{}.log", prefix.as_ref(), name))
+                .with_max_bytes(max_bytes)
+                .with_max_files(max_files)
+        };
+        Self::default()
+            .with_stdout(QemuStdio::piped_with_log(log("stdout")))
+            .with_stderr(QemuStdio::piped_with_log(log("stderr")))
+    }
+
+    pub fn with_stdout(mut self, stdout: QemuStdio) -> Self {
+        self.stdout = stdout;
+        self
+    }
+
+    pub fn with_stderr(mut self, stderr: QemuStdio) -> Self {
+        self.stderr = stderr;
+        self
+    }
+
+    /// Number of lines kept in the output ring buffer of each stream.
+    pub fn with_output_buffer_lines(mut self, lines: usize) -> Self {
+        self.output_buffer_lines = lines;
+        self
+    }
+
+    /// Starts QEMU with an empty environment, apart from the variables named with
+    /// `with_env_inherit` and those set with `with_env`.
+    pub fn with_env_clear(mut self) -> Self {
+        self.env_clear = true;
+        self
+    }
+
+    pub fn with_env_inherit(mut self, key: impl Into<String>) -> Self {
+        self.env_inherit.push(key.into());
+        self
+    }
+
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.envs.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn without_env(mut self, key: impl Into<String>) -> Self {
+        self.env_remove.push(key.into());
+        self
+    }
+
+    pub fn with_current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    pub fn with_umask(mut self, umask: u32) -> Self {
+        self.umask = Some(umask);
+        self
+    }
+
+    /// Switches to this user before exec; supplementary groups are dropped when
+    /// running as root.
+    pub fn with_uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    pub fn with_gid(mut self, gid: u32) -> Self {
+        self.gid = Some(gid);
+        self
+    }
+
+    pub fn with_process_group(mut self, group: QemuProcessGroup) -> Self {
+        self.process_group = group;
+        self
+    }
+
+    /// Signal QEMU receives when the orchestrator dies (`PR_SET_PDEATHSIG`). Linux ties
+    /// this to the spawning thread, so launch from a long-lived runtime thread rather
+    /// than from `spawn_blocking`.
+    pub fn with_parent_death_signal(mut self, signal: i32) -> Self {
+        self.parent_death_signal = Some(signal);
+        self
+    }
+
+    pub fn with_passed_fd(mut self, fd: QemuPassedFd) -> Self {
+        self.passed_fds.push(fd);
+        self
+    }
+
+    /// Applied before switching user, so hard limits can still be raised as root.
+    pub fn with_rlimit(mut self, limit: QemuRlimit) -> Self {
+        self.rlimits
+            .retain(|l| l.get_resource() != limit.get_resource());
+        self.rlimits.push(limit);
+        self
+    }
+
+    pub fn with_cgroup(mut self, cgroup: QemuCgroupConfig) -> Self {
+        self.cgroup = Some(cgroup);
+        self
+    }
+
+    pub fn get_stdout(&self) -> &QemuStdio {
+        &self.stdout
+    }
+
+    pub fn get_stderr(&self) -> &QemuStdio {
+        &self.stderr
+    }
+
+    pub fn get_output_buffer_lines(&self) -> usize {
+        self.output_buffer_lines
+    }
+
+    pub fn get_env_clear(&self) -> bool {
+        self.env_clear
+    }
+
+    pub fn get_env_inherit(&self) -> &[String] {
+        &self.env_inherit
+    }
+
+    pub fn get_envs(&self) -> &[(String, String)] {
+        &self.envs
+    }
+
+    pub fn get_env_remove(&self) -> &[String] {
+        &self.env_remove
+    }
+
+    pub fn get_current_dir(&self) -> Option<&Path> {
+        self.current_dir.as_deref()
+    }
+
+    pub fn get_umask(&self) -> Option<u32> {
+        self.umask
+    }
+
+    pub fn get_uid(&self) -> Option<u32> {
+        self.uid
+    }
+
+    pub fn get_gid(&self) -> Option<u32> {
+        self.gid
+    }
+
+    pub fn get_process_group(&self) -> QemuProcessGroup {
+        self.process_group
+    }
+
+    pub fn get_parent_death_signal(&self) -> Option<i32> {
+        self.parent_death_signal
+    }
+
+    pub fn get_passed_fds(&self) -> &[QemuPassedFd] {
+        &self.passed_fds
+    }
+
+    pub fn get_rlimits(&self) -> &[QemuRlimit] {
+        &self.rlimits
+    }
+
+    pub fn get_cgroup(&self) -> Option<&QemuCgroupConfig> {
+        self.cgroup.as_ref()
+    }
+
+    /// `-add-fd` arguments for the passed descriptors that belong to an fd set.
+    pub fn add_fd_args(&self) -> Vec<QemuArg> {
+        self.passed_fds
+            .iter()
+            .filter_map(|fd| fd.to_add_fd_arg())
+            .collect()
+    }
+}
@@ -1,8 +1,36 @@
+mod adopted_process;
+mod cgroup;
+mod export;
+mod launch_options;
+mod output;
+mod preflight;
+mod probe;
 mod process;
 mod qemu_args;
+mod spawn;
 
 pub mod json;
+pub mod options;
 
-pub use process::QemuProcess;
+pub use cgroup::{
+    QemuCgroup, QemuCgroupConfig, QemuCgroupStats, QemuCpuMax, QemuIoMax, QemuResourceSpec,
+};
+pub use export::{QemuLaunchScript, QemuSystemdUnit};
+pub use launch_options::QemuLaunchOptions;
+pub use output::{QemuOutput, QemuOutputLine, QemuOutputSource, QemuStdio, RotatingLogConfig};
+pub use preflight::{
+    QemuPreflight, QemuPreflightCheck, QemuPreflightKind, QemuPreflightReport, QemuPreflightStatus,
+};
+pub use probe::{
+    QemuBinary, QemuCapabilities, QemuCapabilityKind, QemuDeviceInfo, QemuMachineInfo,
+    QemuMissingCapability, QemuProbeCache, QemuProbeError, QemuVersion, discover_qemu_binaries,
+    find_qemu_binary,
+};
+pub use process::{QemuProcess, read_pidfile};
 pub use qemu_args::QemuArg;
 pub use qemu_args::QemuLaunchArgs;
+pub use qemu_args::QemuParseError;
+pub use qemu_args::{QemuArgChange, QemuArgsDiff, QemuChangeApply, QemuChangeKind};
+pub use qemu_args::{QemuDiagnostic, QemuDiagnosticKind, QemuSeverity};
+pub use qemu_args::{QemuOptionArity, qemu_option_accepts_json, qemu_option_arity};
+pub use spawn::{QemuPassedFd, QemuProcessGroup, QemuRlimit, QemuRlimitResource};
@@ -0,0 +1,100 @@
+use super::QemuOptionError;
+use super::macros::{define_option_enum, impl_try_into_qemu_arg};
+use super::option_props::OptionProps;
+use crate::launcher::QemuArg;
+
+define_option_enum! {
+    AccelType {
+        Kvm => "kvm",
+        Tcg => "tcg",
+        Hvf => "hvf",
+        Whpx => "whpx",
+        Xen => "xen",
+        Nvmm => "nvmm",
+    }
+}
+
+define_option_enum! {
+    AccelThread {
+        Single => "single",
+        Multi => "multi",
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuAccel {
+    accel: AccelType,
+    thread: Option<AccelThread>,
+    tb_size: Option<u32>,
+    extra: Vec<(String, String)>,
+}
+
+impl QemuAccel {
+    pub fn new(accel: AccelType) -> Self {
+        Self {
+            accel,
+            thread: None,
+            tb_size: None,
+            extra: Vec::new(),
+        }
+    }
+
+    pub fn kvm() -> Self {
+        Self::new(AccelType::Kvm)
+    }
+
+    pub fn tcg() -> Self {
+        Self::new(AccelType::Tcg)
+    }
+
+    pub fn with_thread(mut self, thread: AccelThread) -> Self {
+        self.thread = Some(thread);
+        self
+    }
+
+    /// Translation block cache size in MiB (TCG only).
+    pub fn with_tb_size(mut self, tb_size: u32) -> Self {
+        self.tb_size = Some(tb_size);
+        self
+    }
+
+    pub fn with_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn get_accel(&self) -> &AccelType {
+        &self.accel
+    }
+
+    pub fn validate(&self) -> Result<(), QemuOptionError> {
+        if self.accel != AccelType::Tcg {
+            if self.thread.is_some() {
+                return Err(QemuOptionError::invalid(
+                    "-accel",
+                    "thread",
+                    "only supported by tcg",
+                ));
+            }
+            if self.tb_size.is_some() {
+                return Err(QemuOptionError::invalid(
+                    "-accel",
+                    "tb-size",
+                    "only supported by tcg",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_arg(&self) -> Result<QemuArg, QemuOptionError> {
+        self.validate()?;
+        let mut props = OptionProps::new().with_implied(self.accel.as_str());
+        props.push_opt("thread", self.thread.as_ref());
+        props.push_opt("tb-size", self.tb_size);
+        props.extend(&self.extra);
+        Ok(props.into_arg("-accel"))
+    }
+}
+
+impl_try_into_qemu_arg!(QemuAccel);
@@ -0,0 +1,183 @@
+use super::macros::{define_option_enum, impl_try_into_qemu_arg};
+use super::option_props::{OptionProps, validate_id};
+use super::{AioMode, QemuOptionError};
+use crate::launcher::QemuArg;
+
+define_option_enum! {
+    BlockdevDriver {
+        File => "file",
+        HostDevice => "host_device",
+        HostCdrom => "host_cdrom",
+        Nbd => "nbd",
+        Iscsi => "iscsi",
+        Raw => "raw",
+        Qcow2 => "qcow2",
+        Qed => "qed",
+        Vmdk => "vmdk",
+        Vdi => "vdi",
+        Vhdx => "vhdx",
+        Vpc => "vpc",
+    }
+}
+
+define_option_enum! {
+    DiscardMode {
+        Ignore => "ignore",
+        Unmap => "unmap",
+    }
+}
+
+impl BlockdevDriver {
+    /// Protocol drivers access storage directly and take a `filename`.
+    pub fn is_protocol(&self) -> bool {
+        matches!(
+            self,
+            BlockdevDriver::File | BlockdevDriver::HostDevice | BlockdevDriver::HostCdrom
+        )
+    }
+
+    /// Format drivers sit on top of another node referenced by `file`.
+    pub fn is_format(&self) -> bool {
+        matches!(
+            self,
+            BlockdevDriver::Raw
+                | BlockdevDriver::Qcow2
+                | BlockdevDriver::Qed
+                | BlockdevDriver::Vmdk
+                | BlockdevDriver::Vdi
+                | BlockdevDriver::Vhdx
+                | BlockdevDriver::Vpc
+        )
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuBlockdev {
+    driver: BlockdevDriver,
+    node_name: String,
+    filename: Option<String>,
+    file: Option<String>,
+    read_only: Option<bool>,
+    auto_read_only: Option<bool>,
+    cache_direct: Option<bool>,
+    cache_no_flush: Option<bool>,
+    aio: Option<AioMode>,
+    discard: Option<DiscardMode>,
+    extra: Vec<(String, String)>,
+}
+
+impl QemuBlockdev {
+    pub fn new(driver: BlockdevDriver, node_name: impl Into<String>) -> Self {
+        Self {
+            driver,
+            node_name: node_name.into(),
+            filename: None,
+            file: None,
+            read_only: None,
+            auto_read_only: None,
+            cache_direct: None,
+            cache_no_flush: None,
+            aio: None,
+            discard: None,
+            extra: Vec::new(),
+        }
+    }
+
+    /// Path used by protocol drivers such as `file` or `host_device`.
+    pub fn with_filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Node name of the child that a format driver reads from.
+    pub fn with_file_node(mut self, node_name: impl Into<String>) -> Self {
+        self.file = Some(node_name.into());
+        self
+    }
+
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = Some(read_only);
+        self
+    }
+
+    pub fn with_auto_read_only(mut self, auto_read_only: bool) -> Self {
+        self.auto_read_only = Some(auto_read_only);
+        self
+    }
+
+    pub fn with_cache_direct(mut self, direct: bool) -> Self {
+        self.cache_direct = Some(direct);
+        self
+    }
+
+    pub fn with_cache_no_flush(mut self, no_flush: bool) -> Self {
+        self.cache_no_flush = Some(no_flush);
+        self
+    }
+
+    pub fn with_aio(mut self, aio: AioMode) -> Self {
+        self.aio = Some(aio);
+        self
+    }
+
+    pub fn with_discard(mut self, discard: DiscardMode) -> Self {
+        self.discard = Some(discard);
+        self
+    }
+
+    pub fn with_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn get_driver(&self) -> &BlockdevDriver {
+        &self.driver
+    }
+
+    pub fn get_node_name(&self) -> &str {
+        &self.node_name
+    }
+
+    pub fn validate(&self) -> Result<(), QemuOptionError> {
+        validate_id("-blockdev", "node-name", &self.node_name)?;
+        if self.driver.is_protocol() && self.filename.is_none() {
+            return Err(QemuOptionError::MissingProperty {
+                option: "-blockdev",
+                property: "filename",
+            });
+        }
+        if self.driver.is_format() && self.file.is_none() {
+            return Err(QemuOptionError::MissingProperty {
+                option: "-blockdev",
+                property: "file",
+            });
+        }
+        if self.aio == Some(AioMode::Native) && self.cache_direct != Some(true) {
+            return Err(QemuOptionError::invalid(
+                "-blockdev",
+                "aio",
+                "aio=native requires cache.direct=on",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn to_arg(&self) -> Result<QemuArg, QemuOptionError> {
+        self.validate()?;
+        let mut props = OptionProps::new();
+        props.push("driver", self.driver.as_str());
+        props.push("node-name", self.node_name.as_str());
+        props.push_opt("filename", self.filename.as_ref());
+        props.push_opt("file", self.file.as_ref());
+        props.push_bool("read-only", self.read_only);
+        props.push_bool("auto-read-only", self.auto_read_only);
+        props.push_bool("cache.direct", self.cache_direct);
+        props.push_bool("cache.no-flush", self.cache_no_flush);
+        props.push_opt("aio", self.aio.as_ref());
+        props.push_opt("discard", self.discard.as_ref());
+        props.extend(&self.extra);
+        Ok(props.into_arg("-blockdev"))
+    }
+}
+
+impl_try_into_qemu_arg!(QemuBlockdev);
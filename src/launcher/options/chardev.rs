@@ -0,0 +1,173 @@
+use super::QemuOptionError;
+use super::macros::{define_option_enum, impl_try_into_qemu_arg};
+use super::option_props::{OptionProps, validate_id};
+use crate::launcher::QemuArg;
+
+define_option_enum! {
+    ChardevBackend {
+        Socket => "socket",
+        Udp => "udp",
+        File => "file",
+        Pipe => "pipe",
+        Pty => "pty",
+        Stdio => "stdio",
+        Null => "null",
+        Serial => "serial",
+        Parallel => "parallel",
+        Ringbuf => "ringbuf",
+        Spicevmc => "spicevmc",
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuChardev {
+    backend: ChardevBackend,
+    id: String,
+    path: Option<String>,
+    host: Option<String>,
+    port: Option<u16>,
+    server: Option<bool>,
+    wait: Option<bool>,
+    mux: Option<bool>,
+    logfile: Option<String>,
+    extra: Vec<(String, String)>,
+}
+
+impl QemuChardev {
+    pub fn new(backend: ChardevBackend, id: impl Into<String>) -> Self {
+        Self {
+            backend,
+            id: id.into(),
+            path: None,
+            host: None,
+            port: None,
+            server: None,
+            wait: None,
+            mux: None,
+            logfile: None,
+            extra: Vec::new(),
+        }
+    }
+
+    /// A listening Unix socket that does not block QEMU start-up.
+    pub fn unix_server(id: impl Into<String>, path: impl Into<String>) -> Self {
+        Self::new(ChardevBackend::Socket, id)
+            .with_path(path)
+            .with_server(true)
+            .with_wait(false)
+    }
+
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn with_host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    pub fn with_server(mut self, server: bool) -> Self {
+        self.server = Some(server);
+        self
+    }
+
+    pub fn with_wait(mut self, wait: bool) -> Self {
+        self.wait = Some(wait);
+        self
+    }
+
+    pub fn with_mux(mut self, mux: bool) -> Self {
+        self.mux = Some(mux);
+        self
+    }
+
+    pub fn with_logfile(mut self, logfile: impl Into<String>) -> Self {
+        self.logfile = Some(logfile.into());
+        self
+    }
+
+    pub fn with_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn get_backend(&self) -> &ChardevBackend {
+        &self.backend
+    }
+
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn validate(&self) -> Result<(), QemuOptionError> {
+        validate_id("-chardev", "id", &self.id)?;
+        match self.backend {
+            ChardevBackend::Socket => match (&self.path, &self.host, self.port) {
+                (Some(_), None, None) | (None, _, Some(_)) => {}
+                (Some(_), _, _) => {
+                    return Err(QemuOptionError::invalid(
+                        "-chardev",
+                        "path",
+                        "cannot be combined with host/port",
+                    ));
+                }
+                (None, _, None) => {
+                    return Err(QemuOptionError::MissingProperty {
+                        option: "-chardev",
+                        property: "path",
+                    });
+                }
+            },
+            ChardevBackend::File
+            | ChardevBackend::Pipe
+            | ChardevBackend::Serial
+            | ChardevBackend::Parallel
+                if self.path.is_none() =>
+            {
+                return Err(QemuOptionError::MissingProperty {
+                    option: "-chardev",
+                    property: "path",
+                });
+            }
+            ChardevBackend::Udp if self.port.is_none() => {
+                return Err(QemuOptionError::MissingProperty {
+                    option: "-chardev",
+                    property: "port",
+                });
+            }
+            _ => {}
+        }
+        if (self.server.is_some() || self.wait.is_some()) && self.backend != ChardevBackend::Socket
+        {
+            return Err(QemuOptionError::invalid(
+                "-chardev",
+                "server",
+                "only supported by the socket backend",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn to_arg(&self) -> Result<QemuArg, QemuOptionError> {
+        self.validate()?;
+        let mut props = OptionProps::new().with_implied(self.backend.as_str());
+        props.push("id", self.id.as_str());
+        props.push_opt("path", self.path.as_ref());
+        props.push_opt("host", self.host.as_ref());
+        props.push_opt("port", self.port);
+        props.push_bool("server", self.server);
+        props.push_bool("wait", self.wait);
+        props.push_bool("mux", self.mux);
+        props.push_opt("logfile", self.logfile.as_ref());
+        props.extend(&self.extra);
+        Ok(props.into_arg("-chardev"))
+    }
+}
+
+impl_try_into_qemu_arg!(QemuChardev);
@@ -0,0 +1,119 @@
+use super::QemuOptionError;
+use super::macros::impl_try_into_qemu_arg;
+use super::option_props::{OptionProps, require_non_empty, validate_id};
+use crate::launcher::QemuArg;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuDevice {
+    driver: String,
+    id: Option<String>,
+    bus: Option<String>,
+    addr: Option<String>,
+    netdev: Option<String>,
+    drive: Option<String>,
+    chardev: Option<String>,
+    mac: Option<String>,
+    extra: Vec<(String, String)>,
+}
+
+impl QemuDevice {
+    pub fn new(driver: impl Into<String>) -> Self {
+        Self {
+            driver: driver.into(),
+            id: None,
+            bus: None,
+            addr: None,
+            netdev: None,
+            drive: None,
+            chardev: None,
+            mac: None,
+            extra: Vec::new(),
+        }
+    }
+
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn with_bus(mut self, bus: impl Into<String>) -> Self {
+        self.bus = Some(bus.into());
+        self
+    }
+
+    pub fn with_addr(mut self, addr: impl Into<String>) -> Self {
+        self.addr = Some(addr.into());
+        self
+    }
+
+    pub fn with_netdev(mut self, netdev: impl Into<String>) -> Self {
+        self.netdev = Some(netdev.into());
+        self
+    }
+
+    pub fn with_drive(mut self, drive: impl Into<String>) -> Self {
+        self.drive = Some(drive.into());
+        self
+    }
+
+    pub fn with_chardev(mut self, chardev: impl Into<String>) -> Self {
+        self.chardev = Some(chardev.into());
+        self
+    }
+
+    pub fn with_mac(mut self, mac: impl Into<String>) -> Self {
+        self.mac = Some(mac.into());
+        self
+    }
+
+    pub fn with_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn get_driver(&self) -> &str {
+        &self.driver
+    }
+
+    pub fn get_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn validate(&self) -> Result<(), QemuOptionError> {
+        require_non_empty("-device", "driver", &self.driver)?;
+        if let Some(id) = &self.id {
+            validate_id("-device", "id", id)?;
+        }
+        if let Some(mac) = &self.mac {
+            let octets: Vec<&str> = mac.split(':').collect();
+            let valid = octets.len() == 6
+                && octets
+                    .iter()
+                    .all(|o| o.len() == 2 && o.chars().all(|c| c.is_ascii_hexdigit()));
+            if !valid {
+                return Err(QemuOptionError::invalid(
+                    "-device",
+                    "mac",
+                    format!("'{}' is not of the form xx:xx:xx:xx:xx:xx", mac),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_arg(&self) -> Result<QemuArg, QemuOptionError> {
+        self.validate()?;
+        let mut props = OptionProps::new().with_implied(self.driver.as_str());
+        props.push_opt("id", self.id.as_ref());
+        props.push_opt("bus", self.bus.as_ref());
+        props.push_opt("addr", self.addr.as_ref());
+        props.push_opt("netdev", self.netdev.as_ref());
+        props.push_opt("drive", self.drive.as_ref());
+        props.push_opt("chardev", self.chardev.as_ref());
+        props.push_opt("mac", self.mac.as_ref());
+        props.extend(&self.extra);
+        Ok(props.into_arg("-device"))
+    }
+}
+
+impl_try_into_qemu_arg!(QemuDevice);
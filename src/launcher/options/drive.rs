@@ -0,0 +1,176 @@
+use super::QemuOptionError;
+use super::macros::{define_option_enum, impl_try_into_qemu_arg};
+use super::option_props::{OptionProps, validate_id};
+use crate::launcher::QemuArg;
+
+define_option_enum! {
+    /// Image format of a `-drive` or format `-blockdev` node.
+    DriveFormat {
+        Raw => "raw",
+        Qcow2 => "qcow2",
+        Qed => "qed",
+        Vmdk => "vmdk",
+        Vdi => "vdi",
+        Vhdx => "vhdx",
+        Vpc => "vpc",
+    }
+}
+
+define_option_enum! {
+    DriveInterface {
+        Ide => "ide",
+        Scsi => "scsi",
+        Sd => "sd",
+        Mtd => "mtd",
+        Floppy => "floppy",
+        Pflash => "pflash",
+        Virtio => "virtio",
+        None => "none",
+    }
+}
+
+define_option_enum! {
+    DriveMedia {
+        Disk => "disk",
+        Cdrom => "cdrom",
+    }
+}
+
+define_option_enum! {
+    CacheMode {
+        None => "none",
+        Writeback => "writeback",
+        Writethrough => "writethrough",
+        Directsync => "directsync",
+        Unsafe => "unsafe",
+    }
+}
+
+define_option_enum! {
+    AioMode {
+        Threads => "threads",
+        Native => "native",
+        IoUring => "io_uring",
+    }
+}
+
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct QemuDrive {
+    file: Option<String>,
+    id: Option<String>,
+    format: Option<DriveFormat>,
+    interface: Option<DriveInterface>,
+    media: Option<DriveMedia>,
+    index: Option<u32>,
+    cache: Option<CacheMode>,
+    aio: Option<AioMode>,
+    readonly: Option<bool>,
+    snapshot: Option<bool>,
+    extra: Vec<(String, String)>,
+}
+
+impl QemuDrive {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_file(file: impl Into<String>) -> Self {
+        Self::new().with_file(file)
+    }
+
+    pub fn with_file(mut self, file: impl Into<String>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn with_format(mut self, format: DriveFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    pub fn with_interface(mut self, interface: DriveInterface) -> Self {
+        self.interface = Some(interface);
+        self
+    }
+
+    pub fn with_media(mut self, media: DriveMedia) -> Self {
+        self.media = Some(media);
+        self
+    }
+
+    pub fn with_index(mut self, index: u32) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    pub fn with_cache(mut self, cache: CacheMode) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn with_aio(mut self, aio: AioMode) -> Self {
+        self.aio = Some(aio);
+        self
+    }
+
+    pub fn with_readonly(mut self, readonly: bool) -> Self {
+        self.readonly = Some(readonly);
+        self
+    }
+
+    pub fn with_snapshot(mut self, snapshot: bool) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    pub fn with_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), QemuOptionError> {
+        if self.file.is_none() && self.media != Some(DriveMedia::Cdrom) {
+            return Err(QemuOptionError::MissingProperty {
+                option: "-drive",
+                property: "file",
+            });
+        }
+        if let Some(id) = &self.id {
+            validate_id("-drive", "id", id)?;
+        }
+        if self.aio == Some(AioMode::Native)
+            && !matches!(self.cache, Some(CacheMode::None | CacheMode::Directsync))
+        {
+            return Err(QemuOptionError::invalid(
+                "-drive",
+                "aio",
+                "aio=native requires cache=none or cache=directsync",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn to_arg(&self) -> Result<QemuArg, QemuOptionError> {
+        self.validate()?;
+        let mut props = OptionProps::new();
+        props.push_opt("file", self.file.as_ref());
+        props.push_opt("id", self.id.as_ref());
+        props.push_opt("format", self.format.as_ref());
+        props.push_opt("if", self.interface.as_ref());
+        props.push_opt("media", self.media.as_ref());
+        props.push_opt("index", self.index);
+        props.push_opt("cache", self.cache.as_ref());
+        props.push_opt("aio", self.aio.as_ref());
+        props.push_bool("readonly", self.readonly);
+        props.push_bool("snapshot", self.snapshot);
+        props.extend(&self.extra);
+        Ok(props.into_arg("-drive"))
+    }
+}
+
+impl_try_into_qemu_arg!(QemuDrive);
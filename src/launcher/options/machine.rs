@@ -0,0 +1,86 @@
+use super::QemuOptionError;
+use super::macros::impl_try_into_qemu_arg;
+use super::option_props::{OptionProps, require_non_empty};
+use crate::launcher::QemuArg;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuMachine {
+    machine_type: String,
+    memory_backend: Option<String>,
+    usb: Option<bool>,
+    dump_guest_core: Option<bool>,
+    mem_merge: Option<bool>,
+    extra: Vec<(String, String)>,
+}
+
+impl QemuMachine {
+    pub fn new(machine_type: impl Into<String>) -> Self {
+        Self {
+            machine_type: machine_type.into(),
+            memory_backend: None,
+            usb: None,
+            dump_guest_core: None,
+            mem_merge: None,
+            extra: Vec::new(),
+        }
+    }
+
+    pub fn q35() -> Self {
+        Self::new("q35")
+    }
+
+    pub fn pc() -> Self {
+        Self::new("pc")
+    }
+
+    pub fn virt() -> Self {
+        Self::new("virt")
+    }
+
+    /// Id of an `-object memory-backend-*` used as guest RAM.
+    pub fn with_memory_backend(mut self, id: impl Into<String>) -> Self {
+        self.memory_backend = Some(id.into());
+        self
+    }
+
+    pub fn with_usb(mut self, usb: bool) -> Self {
+        self.usb = Some(usb);
+        self
+    }
+
+    pub fn with_dump_guest_core(mut self, dump: bool) -> Self {
+        self.dump_guest_core = Some(dump);
+        self
+    }
+
+    pub fn with_mem_merge(mut self, merge: bool) -> Self {
+        self.mem_merge = Some(merge);
+        self
+    }
+
+    pub fn with_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn get_machine_type(&self) -> &str {
+        &self.machine_type
+    }
+
+    pub fn validate(&self) -> Result<(), QemuOptionError> {
+        require_non_empty("-machine", "type", &self.machine_type)
+    }
+
+    pub fn to_arg(&self) -> Result<QemuArg, QemuOptionError> {
+        self.validate()?;
+        let mut props = OptionProps::new().with_implied(self.machine_type.as_str());
+        props.push_opt("memory-backend", self.memory_backend.as_ref());
+        props.push_bool("usb", self.usb);
+        props.push_bool("dump-guest-core", self.dump_guest_core);
+        props.push_bool("mem-merge", self.mem_merge);
+        props.extend(&self.extra);
+        Ok(props.into_arg("-machine"))
+    }
+}
+
+impl_try_into_qemu_arg!(QemuMachine);
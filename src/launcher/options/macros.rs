@@ -0,0 +1,60 @@
+/// Defines a string-valued enum for a QEMU property with known values and an
+/// `Other` fallback for anything this crate does not know about.
+macro_rules! define_option_enum {
+    ( $(#[$meta:meta])* $name:ident { $( $variant:ident => $value:expr ),* $(,)? } ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+        pub enum $name {
+            $( $variant, )*
+            Other(String),
+        }
+
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $( $name::$variant => $value, )*
+                    $name::Other(s) => s,
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(s: &str) -> Self {
+                match s {
+                    $( $value => $name::$variant, )*
+                    other => $name::Other(other.to_string()),
+                }
+            }
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+/// Implements `TryFrom<$ty> for QemuArg` in terms of the builder's `to_arg`.
+macro_rules! impl_try_into_qemu_arg {
+    ( $( $ty:ty ),* $(,)? ) => {
+        $(
+            impl TryFrom<$ty> for $crate::launcher::QemuArg {
+                type Error = $crate::launcher::options::QemuOptionError;
+                fn try_from(option: $ty) -> Result<Self, Self::Error> {
+                    option.to_arg()
+                }
+            }
+
+            impl TryFrom<&$ty> for $crate::launcher::QemuArg {
+                type Error = $crate::launcher::options::QemuOptionError;
+                fn try_from(option: &$ty) -> Result<Self, Self::Error> {
+                    option.to_arg()
+                }
+            }
+        )*
+    };
+}
+
+pub(crate) use define_option_enum;
+pub(crate) use impl_try_into_qemu_arg;
@@ -0,0 +1,78 @@
+use super::QemuOptionError;
+use super::macros::impl_try_into_qemu_arg;
+use super::option_props::OptionProps;
+use crate::launcher::QemuArg;
+
+/// Guest RAM (`-m`). Sizes are kept in MiB.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuMemory {
+    size_mib: u64,
+    slots: Option<u32>,
+    maxmem_mib: Option<u64>,
+}
+
+impl QemuMemory {
+    pub fn from_mib(size_mib: u64) -> Self {
+        Self {
+            size_mib,
+            slots: None,
+            maxmem_mib: None,
+        }
+    }
+
+    pub fn from_gib(size_gib: u64) -> Self {
+        Self::from_mib(size_gib * 1024)
+    }
+
+    /// Enables memory hotplug up to `maxmem_mib` across `slots` DIMM slots.
+    pub fn with_hotplug(mut self, slots: u32, maxmem_mib: u64) -> Self {
+        self.slots = Some(slots);
+        self.maxmem_mib = Some(maxmem_mib);
+        self
+    }
+
+    pub fn get_size_mib(&self) -> u64 {
+        self.size_mib
+    }
+
+    pub fn validate(&self) -> Result<(), QemuOptionError> {
+        if self.size_mib == 0 {
+            return Err(QemuOptionError::invalid(
+                "-m",
+                "size",
+                "must be greater than 0",
+            ));
+        }
+        match (self.slots, self.maxmem_mib) {
+            (Some(_), None) => Err(QemuOptionError::MissingProperty {
+                option: "-m",
+                property: "maxmem",
+            }),
+            (None, Some(_)) => Err(QemuOptionError::MissingProperty {
+                option: "-m",
+                property: "slots",
+            }),
+            (Some(_), Some(maxmem)) if maxmem < self.size_mib => Err(QemuOptionError::invalid(
+                "-m",
+                "maxmem",
+                format!("{}M is less than size {}M", maxmem, self.size_mib),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn to_arg(&self) -> Result<QemuArg, QemuOptionError> {
+        self.validate()?;
+        let mut props = OptionProps::new();
+        if self.slots.is_none() {
+            props = props.with_implied(format!("{}M", self.size_mib));
+        } else {
+            props.push("size", format!("{}M", self.size_mib));
+        }
+        props.push_opt("slots", self.slots);
+        props.push_opt("maxmem", self.maxmem_mib.map(|m| format!("{}M", m)));
+        Ok(props.into_arg("-m"))
+    }
+}
+
+impl_try_into_qemu_arg!(QemuMemory);
@@ -0,0 +1,27 @@
+mod macros;
+
+mod accel;
+mod blockdev;
+mod chardev;
+mod device;
+mod drive;
+mod machine;
+mod memory;
+mod netdev;
+mod object;
+mod option_error;
+mod option_props;
+mod smp;
+
+pub use accel::{AccelThread, AccelType, QemuAccel};
+pub use blockdev::{BlockdevDriver, DiscardMode, QemuBlockdev};
+pub use chardev::{ChardevBackend, QemuChardev};
+pub use device::QemuDevice;
+pub use drive::{AioMode, CacheMode, DriveFormat, DriveInterface, DriveMedia, QemuDrive};
+pub use machine::QemuMachine;
+pub use memory::QemuMemory;
+pub use netdev::{NetdevBackend, QemuNetdev};
+pub use object::QemuObject;
+pub use option_error::QemuOptionError;
+pub use option_props::escape_option_value;
+pub use smp::QemuSmp;
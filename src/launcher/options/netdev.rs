@@ -0,0 +1,148 @@
+use super::QemuOptionError;
+use super::macros::{define_option_enum, impl_try_into_qemu_arg};
+use super::option_props::{OptionProps, validate_id};
+use crate::launcher::QemuArg;
+
+define_option_enum! {
+    NetdevBackend {
+        User => "user",
+        Tap => "tap",
+        Bridge => "bridge",
+        Socket => "socket",
+        Stream => "stream",
+        Dgram => "dgram",
+        VhostUser => "vhost-user",
+        VhostVdpa => "vhost-vdpa",
+        L2tpv3 => "l2tpv3",
+        Hubport => "hubport",
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuNetdev {
+    backend: NetdevBackend,
+    id: String,
+    ifname: Option<String>,
+    script: Option<String>,
+    downscript: Option<String>,
+    br: Option<String>,
+    vhost: Option<bool>,
+    chardev: Option<String>,
+    hostfwd: Vec<String>,
+    extra: Vec<(String, String)>,
+}
+
+impl QemuNetdev {
+    pub fn new(backend: NetdevBackend, id: impl Into<String>) -> Self {
+        Self {
+            backend,
+            id: id.into(),
+            ifname: None,
+            script: None,
+            downscript: None,
+            br: None,
+            vhost: None,
+            chardev: None,
+            hostfwd: Vec::new(),
+            extra: Vec::new(),
+        }
+    }
+
+    pub fn user(id: impl Into<String>) -> Self {
+        Self::new(NetdevBackend::User, id)
+    }
+
+    pub fn tap(id: impl Into<String>) -> Self {
+        Self::new(NetdevBackend::Tap, id)
+    }
+
+    pub fn bridge(id: impl Into<String>, br: impl Into<String>) -> Self {
+        Self::new(NetdevBackend::Bridge, id).with_bridge(br)
+    }
+
+    pub fn with_ifname(mut self, ifname: impl Into<String>) -> Self {
+        self.ifname = Some(ifname.into());
+        self
+    }
+
+    pub fn with_script(mut self, script: impl Into<String>) -> Self {
+        self.script = Some(script.into());
+        self
+    }
+
+    pub fn with_downscript(mut self, downscript: impl Into<String>) -> Self {
+        self.downscript = Some(downscript.into());
+        self
+    }
+
+    pub fn with_bridge(mut self, br: impl Into<String>) -> Self {
+        self.br = Some(br.into());
+        self
+    }
+
+    pub fn with_vhost(mut self, vhost: bool) -> Self {
+        self.vhost = Some(vhost);
+        self
+    }
+
+    pub fn with_chardev(mut self, chardev: impl Into<String>) -> Self {
+        self.chardev = Some(chardev.into());
+        self
+    }
+
+    /// Adds a `hostfwd` rule for the `user` backend, e.g. `tcp::2222-:22`.
+    pub fn with_hostfwd(mut self, rule: impl Into<String>) -> Self {
+        self.hostfwd.push(rule.into());
+        self
+    }
+
+    pub fn with_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn get_backend(&self) -> &NetdevBackend {
+        &self.backend
+    }
+
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn validate(&self) -> Result<(), QemuOptionError> {
+        validate_id("-netdev", "id", &self.id)?;
+        if !self.hostfwd.is_empty() && self.backend != NetdevBackend::User {
+            return Err(QemuOptionError::invalid(
+                "-netdev",
+                "hostfwd",
+                "only supported by the user backend",
+            ));
+        }
+        if self.backend == NetdevBackend::VhostUser && self.chardev.is_none() {
+            return Err(QemuOptionError::MissingProperty {
+                option: "-netdev",
+                property: "chardev",
+            });
+        }
+        Ok(())
+    }
+
+    pub fn to_arg(&self) -> Result<QemuArg, QemuOptionError> {
+        self.validate()?;
+        let mut props = OptionProps::new().with_implied(self.backend.as_str());
+        props.push("id", self.id.as_str());
+        props.push_opt("ifname", self.ifname.as_ref());
+        props.push_opt("script", self.script.as_ref());
+        props.push_opt("downscript", self.downscript.as_ref());
+        props.push_opt("br", self.br.as_ref());
+        props.push_bool("vhost", self.vhost);
+        props.push_opt("chardev", self.chardev.as_ref());
+        for rule in &self.hostfwd {
+            props.push("hostfwd", rule.as_str());
+        }
+        props.extend(&self.extra);
+        Ok(props.into_arg("-netdev"))
+    }
+}
+
+impl_try_into_qemu_arg!(QemuNetdev);
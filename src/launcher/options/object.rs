@@ -0,0 +1,84 @@
+use super::QemuOptionError;
+use super::macros::impl_try_into_qemu_arg;
+use super::option_props::{OptionProps, require_non_empty, validate_id};
+use crate::launcher::QemuArg;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuObject {
+    qom_type: String,
+    id: String,
+    props: Vec<(String, String)>,
+}
+
+impl QemuObject {
+    pub fn new(qom_type: impl Into<String>, id: impl Into<String>) -> Self {
+        Self {
+            qom_type: qom_type.into(),
+            id: id.into(),
+            props: Vec::new(),
+        }
+    }
+
+    pub fn memory_backend_ram(id: impl Into<String>, size_mib: u64) -> Self {
+        Self::new("memory-backend-ram", id).with_property("size", format!("{}M", size_mib))
+    }
+
+    pub fn memory_backend_file(
+        id: impl Into<String>,
+        mem_path: impl Into<String>,
+        size_mib: u64,
+    ) -> Self {
+        Self::new("memory-backend-file", id)
+            .with_property("mem-path", mem_path)
+            .with_property("size", format!("{}M", size_mib))
+    }
+
+    pub fn rng_random(id: impl Into<String>, filename: impl Into<String>) -> Self {
+        Self::new("rng-random", id).with_property("filename", filename)
+    }
+
+    pub fn with_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.props.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn get_qom_type(&self) -> &str {
+        &self.qom_type
+    }
+
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn validate(&self) -> Result<(), QemuOptionError> {
+        require_non_empty("-object", "qom-type", &self.qom_type)?;
+        validate_id("-object", "id", &self.id)?;
+        if self.qom_type.starts_with("memory-backend-")
+            && !self.props.iter().any(|(k, _)| k == "size")
+        {
+            return Err(QemuOptionError::MissingProperty {
+                option: "-object",
+                property: "size",
+            });
+        }
+        if self.qom_type == "memory-backend-file"
+            && !self.props.iter().any(|(k, _)| k == "mem-path")
+        {
+            return Err(QemuOptionError::MissingProperty {
+                option: "-object",
+                property: "mem-path",
+            });
+        }
+        Ok(())
+    }
+
+    pub fn to_arg(&self) -> Result<QemuArg, QemuOptionError> {
+        self.validate()?;
+        let mut props = OptionProps::new().with_implied(self.qom_type.as_str());
+        props.push("id", self.id.as_str());
+        props.extend(&self.props);
+        Ok(props.into_arg("-object"))
+    }
+}
+
+impl_try_into_qemu_arg!(QemuObject);
@@ -0,0 +1,47 @@
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum QemuOptionError {
+    MissingProperty {
+        option: &'static str,
+        property: &'static str,
+    },
+    InvalidValue {
+        option: &'static str,
+        property: &'static str,
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for QemuOptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QemuOptionError::MissingProperty { option, property } => {
+                write!(f, "{}: missing required property '{}'", option, property)
+            }
+            QemuOptionError::InvalidValue {
+                option,
+                property,
+                reason,
+            } => write!(
+                f,
+                "{}: invalid value for '{}': {}",
+                option, property, reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for QemuOptionError {}
+
+impl QemuOptionError {
+    pub(crate) fn invalid(
+        option: &'static str,
+        property: &'static str,
+        reason: impl Into<String>,
+    ) -> Self {
+        QemuOptionError::InvalidValue {
+            option,
+            property,
+            reason: reason.into(),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use super::QemuOptionError;
+use crate::launcher::QemuArg;
+
+/// Escapes a value for QEMU's `key=value,...` option syntax, where a literal
+/// comma has to be written as `,,`.
+pub fn escape_option_value(value: &str) -> String {
+    value.replace(',', ",,")
+}
+
+/// Ordered property list used by the typed builders to render a `QemuArg`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OptionProps {
+    implied: Option<String>,
+    props: Vec<(String, String)>,
+}
+
+impl OptionProps {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// The leading value without a key, e.g. the driver in `-device virtio-net-pci,...`.
+    pub(crate) fn with_implied(mut self, value: impl Into<String>) -> Self {
+        self.implied = Some(value.into());
+        self
+    }
+
+    pub(crate) fn push(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.props.push((key.into(), value.into()));
+    }
+
+    pub(crate) fn push_opt<T: ToString>(&mut self, key: &str, value: Option<T>) {
+        if let Some(value) = value {
+            self.push(key, value.to_string());
+        }
+    }
+
+    pub(crate) fn push_bool(&mut self, key: &str, value: Option<bool>) {
+        if let Some(value) = value {
+            self.push(key, if value { "on" } else { "off" });
+        }
+    }
+
+    pub(crate) fn extend(&mut self, extra: &[(String, String)]) {
+        self.props.extend(extra.iter().cloned());
+    }
+
+    pub(crate) fn into_arg(self, option: &str) -> QemuArg {
+        let mut items = Vec::with_capacity(self.props.len() + 1);
+        if let Some(implied) = &self.implied {
+            items.push(escape_option_value(implied));
+        }
+        for (key, value) in &self.props {
+            items.push(format!("{}={}", key, escape_option_value(value)));
+        }
+        if items.len() == 1 && self.props.is_empty() {
+            QemuArg::from_key_value(option, items.remove(0))
+        } else {
+            QemuArg::from_list(option, items)
+        }
+    }
+}
+
+/// Checks an id against QEMU's rules: a letter followed by letters, digits, `-`, `.` or `_`.
+pub(crate) fn validate_id(
+    option: &'static str,
+    property: &'static str,
+    id: &str,
+) -> Result<(), QemuOptionError> {
+    let mut chars = id.chars();
+    match chars.next() {
+        None => Err(QemuOptionError::MissingProperty { option, property }),
+        Some(c) if !c.is_ascii_alphabetic() => Err(QemuOptionError::invalid(
+            option,
+            property,
+            format!("'{}' must start with a letter", id),
+        )),
+        _ if !chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_')) => {
+            Err(QemuOptionError::invalid(
+                option,
+                property,
+                format!(
+                    "'{}' may only contain letters, digits, '-', '.' and '_'",
+                    id
+                ),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn require_non_empty(
+    option: &'static str,
+    property: &'static str,
+    value: &str,
+) -> Result<(), QemuOptionError> {
+    if value.is_empty() {
+        Err(QemuOptionError::MissingProperty { option, property })
+    } else {
+        Ok(())
+    }
+}
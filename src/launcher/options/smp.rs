@@ -0,0 +1,137 @@
+use super::QemuOptionError;
+use super::macros::impl_try_into_qemu_arg;
+use super::option_props::OptionProps;
+use crate::launcher::QemuArg;
+
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct QemuSmp {
+    cpus: Option<u32>,
+    maxcpus: Option<u32>,
+    sockets: Option<u32>,
+    dies: Option<u32>,
+    clusters: Option<u32>,
+    cores: Option<u32>,
+    threads: Option<u32>,
+}
+
+impl QemuSmp {
+    pub fn new(cpus: u32) -> Self {
+        Self {
+            cpus: Some(cpus),
+            ..Self::default()
+        }
+    }
+
+    /// Topology only; QEMU derives the CPU count from it.
+    pub fn topology(sockets: u32, cores: u32, threads: u32) -> Self {
+        Self {
+            sockets: Some(sockets),
+            cores: Some(cores),
+            threads: Some(threads),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_cpus(mut self, cpus: u32) -> Self {
+        self.cpus = Some(cpus);
+        self
+    }
+
+    pub fn with_maxcpus(mut self, maxcpus: u32) -> Self {
+        self.maxcpus = Some(maxcpus);
+        self
+    }
+
+    pub fn with_sockets(mut self, sockets: u32) -> Self {
+        self.sockets = Some(sockets);
+        self
+    }
+
+    pub fn with_dies(mut self, dies: u32) -> Self {
+        self.dies = Some(dies);
+        self
+    }
+
+    pub fn with_clusters(mut self, clusters: u32) -> Self {
+        self.clusters = Some(clusters);
+        self
+    }
+
+    pub fn with_cores(mut self, cores: u32) -> Self {
+        self.cores = Some(cores);
+        self
+    }
+
+    pub fn with_threads(mut self, threads: u32) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    fn fields(&self) -> [(&'static str, Option<u32>); 7] {
+        [
+            ("cpus", self.cpus),
+            ("maxcpus", self.maxcpus),
+            ("sockets", self.sockets),
+            ("dies", self.dies),
+            ("clusters", self.clusters),
+            ("cores", self.cores),
+            ("threads", self.threads),
+        ]
+    }
+
+    pub fn validate(&self) -> Result<(), QemuOptionError> {
+        let fields = self.fields();
+        if fields.iter().all(|(_, v)| v.is_none()) {
+            return Err(QemuOptionError::MissingProperty {
+                option: "-smp",
+                property: "cpus",
+            });
+        }
+        if let Some((name, _)) = fields.iter().find(|(_, v)| *v == Some(0)) {
+            return Err(QemuOptionError::invalid(
+                "-smp",
+                name,
+                "must be greater than 0",
+            ));
+        }
+        if let (Some(cpus), Some(maxcpus)) = (self.cpus, self.maxcpus)
+            && cpus > maxcpus
+        {
+            return Err(QemuOptionError::invalid(
+                "-smp",
+                "maxcpus",
+                format!("maxcpus ({}) is less than cpus ({})", maxcpus, cpus),
+            ));
+        }
+        if let (Some(sockets), Some(cores), Some(threads)) =
+            (self.sockets, self.cores, self.threads)
+        {
+            let product = sockets as u64
+                * self.dies.unwrap_or(1) as u64
+                * self.clusters.unwrap_or(1) as u64
+                * cores as u64
+                * threads as u64;
+            if let Some(expected) = self.maxcpus.or(self.cpus)
+                && product != expected as u64
+            {
+                return Err(QemuOptionError::invalid(
+                    "-smp",
+                    "sockets",
+                    format!("topology has {} CPUs but maxcpus is {}", product, expected),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_arg(&self) -> Result<QemuArg, QemuOptionError> {
+        self.validate()?;
+        let mut props = OptionProps::new();
+        for (name, value) in self.fields() {
+            props.push_opt(name, value);
+        }
+        Ok(props.into_arg("-smp"))
+    }
+}
+
+impl_try_into_qemu_arg!(QemuSmp);
@@ -0,0 +1,7 @@
+mod qemu_output;
+mod qemu_stdio;
+mod rotating_log;
+
+pub use qemu_output::{QemuOutput, QemuOutputLine, QemuOutputSource};
+pub use qemu_stdio::QemuStdio;
+pub use rotating_log::RotatingLogConfig;
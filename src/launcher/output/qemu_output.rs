@@ -0,0 +1,229 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use futures::Stream;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use super::RotatingLogConfig;
+use super::rotating_log::RotatingLogWriter;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum QemuOutputSource {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuOutputLine {
+    pub source: QemuOutputSource,
+    pub line: String,
+    pub time: SystemTime,
+}
+
+/// Bytes kept of a single line; the rest of a longer line is dropped.
+const MAX_LINE_BYTES: usize = 16 * 1024;
+
+/// The most recent lines of each stream, numbered in arrival order across both.
+#[derive(Debug, Default)]
+struct LineBuffers {
+    next: u64,
+    stdout: VecDeque<(u64, QemuOutputLine)>,
+    stderr: VecDeque<(u64, QemuOutputLine)>,
+}
+
+impl LineBuffers {
+    fn get(&self, source: QemuOutputSource) -> &VecDeque<(u64, QemuOutputLine)> {
+        match source {
+            QemuOutputSource::Stdout => &self.stdout,
+            QemuOutputSource::Stderr => &self.stderr,
+        }
+    }
+
+    fn push(&mut self, line: QemuOutputLine, capacity: usize) {
+        let seq = self.next;
+        self.next += 1;
+        let buffer = match line.source {
+            QemuOutputSource::Stdout => &mut self.stdout,
+            QemuOutputSource::Stderr => &mut self.stderr,
+        };
+        if capacity > 0 {
+            if buffer.len() == capacity {
+                buffer.pop_front();
+            }
+            buffer.push_back((seq, line));
+        }
+    }
+}
+
+/// Output captured from QEMU's piped stdout/stderr: a bounded ring buffer of the
+/// most recent lines of each stream plus a broadcast of new lines as they arrive.
+/// Separate buffers keep stdout chatter from evicting the stderr lines needed to
+/// diagnose a crash.
+#[derive(Debug)]
+pub struct QemuOutput {
+    buffers: Arc<Mutex<LineBuffers>>,
+    capacity: usize,
+    sources: Vec<QemuOutputSource>,
+    sender: broadcast::Sender<QemuOutputLine>,
+    readers: Vec<JoinHandle<()>>,
+}
+
+impl QemuOutput {
+    pub(crate) fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(256);
+        Self {
+            buffers: Arc::new(Mutex::new(LineBuffers::default())),
+            capacity,
+            sources: Vec::new(),
+            sender,
+            readers: Vec::new(),
+        }
+    }
+
+    /// Spawns a task that reads `reader` line by line until EOF.
+    pub(crate) fn attach<Rd>(
+        &mut self,
+        reader: Rd,
+        source: QemuOutputSource,
+        log: Option<RotatingLogConfig>,
+    ) where
+        Rd: AsyncRead + Unpin + Send + 'static,
+    {
+        self.sources.push(source);
+        let buffers = self.buffers.clone();
+        let capacity = self.capacity;
+        let sender = self.sender.clone();
+        self.readers.push(tokio::spawn(async move {
+            let mut log = match log {
+                Some(config) => match RotatingLogWriter::open(config).await {
+                    Ok(writer) => Some(writer),
+                    Err(e) => {
+                        log::warn!("QemuOutput: cannot open log file: {}", e);
+                        None
+                    }
+                },
+                None => None,
+            };
+
+            let mut reader = BufReader::new(reader);
+            let mut raw = Vec::new();
+            loop {
+                raw.clear();
+                match read_capped_line(&mut reader, &mut raw).await {
+                    Ok(0) => break,
+                    Ok(_) => {}
+                    Err(e) => {
+                        log::error!("QemuOutput: read error: {}", e);
+                        break;
+                    }
+                }
+                let text = String::from_utf8_lossy(&raw);
+                let text = text.trim_end_matches(['\n', '\r']);
+
+                if let Some(writer) = &mut log
+                    && let Err(e) = writer.write_line(text).await
+                {
+                    log::warn!("QemuOutput: cannot write log file: {}", e);
+                    log = None;
+                }
+
+                let line = QemuOutputLine {
+                    source,
+                    line: text.to_string(),
+                    time: SystemTime::now(),
+                };
+                // Sent under the lock, so a snapshot taken after subscribing holds
+                // exactly the first lines a subscriber receives, in the same order.
+                let mut buffers = buffers.lock().unwrap();
+                buffers.push(line.clone(), capacity);
+                let _ = sender.send(line);
+            }
+        }));
+    }
+
+    /// Whether `source` is being captured.
+    pub fn captures(&self, source: QemuOutputSource) -> bool {
+        self.sources.contains(&source)
+    }
+
+    /// Lines received from now on. Lines a slow consumer falls behind on are skipped.
+    pub fn lines(&self) -> impl Stream<Item = QemuOutputLine> + Send + 'static {
+        futures::stream::unfold(self.sender.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(line) => return Some((line, rx)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    /// All lines currently held in the ring buffers, oldest first.
+    pub fn snapshot(&self) -> Vec<QemuOutputLine> {
+        let buffers = self.buffers.lock().unwrap();
+        merged(&buffers, None).map(|(_, l)| l.clone()).collect()
+    }
+
+    /// The last `count` lines from `source` (or from both streams if `None`).
+    pub fn tail(&self, source: Option<QemuOutputSource>, count: usize) -> Vec<String> {
+        let buffers = self.buffers.lock().unwrap();
+        let lines: Vec<String> = merged(&buffers, source)
+            .map(|(_, l)| l.line.clone())
+            .collect();
+        lines[lines.len().saturating_sub(count)..].to_vec()
+    }
+
+    /// Waits until every captured stream has reached EOF.
+    pub async fn wait_closed(&mut self) {
+        for reader in self.readers.drain(..) {
+            let _ = reader.await;
+        }
+    }
+}
+
+/// The buffered lines of `source` (or both streams), in arrival order.
+fn merged(
+    buffers: &LineBuffers,
+    source: Option<QemuOutputSource>,
+) -> impl Iterator<Item = &(u64, QemuOutputLine)> {
+    let mut lines: Vec<&(u64, QemuOutputLine)> =
+        [QemuOutputSource::Stdout, QemuOutputSource::Stderr]
+            .into_iter()
+            .filter(|s| source.is_none_or(|source| source == *s))
+            .flat_map(|s| buffers.get(s))
+            .collect();
+    lines.sort_by_key(|(seq, _)| *seq);
+    lines.into_iter()
+}
+
+/// Like `read_until(b'\n')`, but keeps at most `MAX_LINE_BYTES` of the line and
+/// discards the rest, so a stream without newlines cannot grow memory unbounded.
+/// Returns the number of bytes consumed.
+async fn read_capped_line<Rd>(reader: &mut Rd, raw: &mut Vec<u8>) -> std::io::Result<usize>
+where
+    Rd: AsyncBufRead + Unpin,
+{
+    let mut consumed = 0;
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            return Ok(consumed);
+        }
+        let (chunk, done) = match available.iter().position(|&b| b == b'\n') {
+            Some(end) => (&available[..=end], true),
+            None => (available, false),
+        };
+        let room = MAX_LINE_BYTES.saturating_sub(raw.len());
+        raw.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        let used = chunk.len();
+        reader.consume(used);
+        consumed += used;
+        if done {
+            return Ok(consumed);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+
+use super::RotatingLogConfig;
+
+/// Where QEMU's stdout or stderr goes.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub enum QemuStdio {
+    #[default]
+    Inherit,
+    Null,
+    /// Appends to the given file.
+    File(PathBuf),
+    /// Captures the output into the process' `QemuOutput`, optionally mirrored to a
+    /// rotating log file.
+    Pipe(Option<RotatingLogConfig>),
+}
+
+impl QemuStdio {
+    pub fn piped() -> Self {
+        QemuStdio::Pipe(None)
+    }
+
+    pub fn piped_with_log(log: RotatingLogConfig) -> Self {
+        QemuStdio::Pipe(Some(log))
+    }
+
+    pub fn is_piped(&self) -> bool {
+        matches!(self, QemuStdio::Pipe(_))
+    }
+
+    pub(crate) fn to_stdio(&self) -> std::io::Result<std::process::Stdio> {
+        use std::process::Stdio;
+        Ok(match self {
+            QemuStdio::Inherit => Stdio::inherit(),
+            QemuStdio::Null => Stdio::null(),
+            QemuStdio::File(path) => Stdio::from(
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?,
+            ),
+            QemuStdio::Pipe(_) => Stdio::piped(),
+        })
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::{Path, PathBuf};
+
+use tokio::io::AsyncWriteExt;
+
+/// A log file that is rotated to `<path>.1`, `<path>.2`, ... once it exceeds `max_bytes`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct RotatingLogConfig {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl RotatingLogConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Number of rotated files kept besides the active one.
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get_max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    pub fn get_max_files(&self) -> usize {
+        self.max_files
+    }
+}
+
+pub(crate) struct RotatingLogWriter {
+    config: RotatingLogConfig,
+    file: tokio::fs::File,
+    size: u64,
+}
+
+impl RotatingLogWriter {
+    pub(crate) async fn open(config: RotatingLogConfig) -> std::io::Result<Self> {
+        let file = Self::open_file(&config.path).await?;
+        let size = file.metadata().await?.len();
+        Ok(Self { config, file, size })
+    }
+
+    async fn open_file(path: &Path) -> std::io::Result<tokio::fs::File> {
+        tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.config.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    async fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush().await?;
+        if self.config.max_files == 0 {
+            self.file.set_len(0).await?;
+        } else {
+            for index in (1..self.config.max_files).rev() {
+                match tokio::fs::rename(self.rotated_path(index), self.rotated_path(index + 1))
+                    .await
+                {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+            tokio::fs::rename(&self.config.path, self.rotated_path(1)).await?;
+            self.file = Self::open_file(&self.config.path).await?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+
+    pub(crate) async fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.config.max_bytes {
+            self.rotate().await?;
+        }
+        self.file.write_all(line.as_bytes()).await?;
+        self.file.write_all(b"\n").await?;
+        self.size += len;
+        Ok(())
+    }
+}
@@ -0,0 +1,8 @@
+mod preflight_report;
+mod qemu_preflight;
+
+pub use preflight_report::{
+    QemuPreflightCheck, QemuPreflightKind, QemuPreflightReport, QemuPreflightStatus,
+};
+pub use qemu_preflight::QemuPreflight;
+pub(crate) use qemu_preflight::unix_sockets;
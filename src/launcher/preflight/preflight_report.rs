@@ -0,0 +1,114 @@
+use crate::launcher::QemuLaunchArgs;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QemuPreflightStatus {
+    Passed,
+    Warning,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum QemuPreflightKind {
+    Kvm,
+    NestedVirtualization,
+    Hugepages,
+    DiskImage,
+    Socket,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuPreflightCheck {
+    pub kind: QemuPreflightKind,
+    pub status: QemuPreflightStatus,
+    /// What was checked, e.g. a path.
+    pub subject: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for QemuPreflightCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self.status {
+            QemuPreflightStatus::Passed => "ok",
+            QemuPreflightStatus::Warning => "warning",
+            QemuPreflightStatus::Failed => "failed",
+        };
+        write!(f, "[{}] {}: {}", status, self.subject, self.message)
+    }
+}
+
+/// Result of `QemuPreflight::run`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QemuPreflightReport {
+    checks: Vec<QemuPreflightCheck>,
+    adjusted_args: Option<QemuLaunchArgs>,
+}
+
+impl QemuPreflightReport {
+    pub(crate) fn push(
+        &mut self,
+        kind: QemuPreflightKind,
+        status: QemuPreflightStatus,
+        subject: impl Into<String>,
+        message: impl Into<String>,
+    ) {
+        self.checks.push(QemuPreflightCheck {
+            kind,
+            status,
+            subject: subject.into(),
+            message: message.into(),
+        });
+    }
+
+    pub(crate) fn set_adjusted_args(&mut self, args: QemuLaunchArgs) {
+        self.adjusted_args = Some(args);
+    }
+
+    /// No check failed (warnings are allowed).
+    pub fn is_ok(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    pub fn get_checks(&self) -> &[QemuPreflightCheck] {
+        &self.checks
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &QemuPreflightCheck> {
+        self.checks
+            .iter()
+            .filter(|c| c.status == QemuPreflightStatus::Failed)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &QemuPreflightCheck> {
+        self.checks
+            .iter()
+            .filter(|c| c.status == QemuPreflightStatus::Warning)
+    }
+
+    /// The launch args to use instead of the checked ones, when a fallback (such as
+    /// KVM to TCG) was applied.
+    pub fn get_adjusted_args(&self) -> Option<&QemuLaunchArgs> {
+        self.adjusted_args.as_ref()
+    }
+}
+
+/// Lists failures and warnings, one per line.
+impl std::fmt::Display for QemuPreflightReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut first = true;
+        for check in self
+            .checks
+            .iter()
+            .filter(|c| c.status != QemuPreflightStatus::Passed)
+        {
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+            write!(f, "{}", check)?;
+        }
+        if first {
+            write!(f, "all preflight checks passed")?;
+        }
+        Ok(())
+    }
+}
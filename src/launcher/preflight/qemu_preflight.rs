@@ -0,0 +1,536 @@
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+
+use super::{QemuPreflightKind, QemuPreflightReport, QemuPreflightStatus};
+use crate::launcher::qemu_args::{canonical_option_name, parse_size};
+use crate::launcher::{QemuArg, QemuLaunchArgs};
+
+use QemuPreflightKind::{DiskImage, Hugepages, Kvm, NestedVirtualization, Socket};
+use QemuPreflightStatus::{Failed, Passed, Warning};
+
+/// QEMU's default RAM size when `-m` is not given.
+const DEFAULT_RAM_BYTES: u64 = 128 << 20;
+
+/// Host checks run before spawning QEMU, so that problems show up as a structured
+/// report rather than as a QEMU exit with an error on stderr.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuPreflight {
+    kvm_device: PathBuf,
+    accel_fallback: bool,
+}
+
+impl Default for QemuPreflight {
+    fn default() -> Self {
+        Self {
+            kvm_device: PathBuf::from("/dev/kvm"),
+            accel_fallback: false,
+        }
+    }
+}
+
+impl QemuPreflight {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// When KVM is required but unusable, switch the args to TCG (reported as a
+    /// warning, with the rewritten args in `QemuPreflightReport::get_adjusted_args`)
+    /// instead of failing.
+    pub fn with_accel_fallback(mut self, fallback: bool) -> Self {
+        self.accel_fallback = fallback;
+        self
+    }
+
+    pub fn with_kvm_device(mut self, path: impl Into<PathBuf>) -> Self {
+        self.kvm_device = path.into();
+        self
+    }
+
+    pub fn get_accel_fallback(&self) -> bool {
+        self.accel_fallback
+    }
+
+    pub fn get_kvm_device(&self) -> &Path {
+        &self.kvm_device
+    }
+
+    /// Checks are made as the current user; if QEMU is started under another uid
+    /// (`QemuLaunchOptions::with_uid`), file permissions may differ for it.
+    pub fn run(&self, args: &QemuLaunchArgs) -> QemuPreflightReport {
+        let mut report = QemuPreflightReport::default();
+        self.check_kvm(args, &mut report);
+        check_nested(args, &mut report);
+        check_hugepages(args, &mut report);
+        check_disks(args, &mut report);
+        check_sockets(args, &mut report);
+        report
+    }
+
+    fn check_kvm(&self, args: &QemuLaunchArgs, report: &mut QemuPreflightReport) {
+        let required = args.get_args().iter().any(requires_kvm);
+        let preferred = args.get_args().iter().any(|a| {
+            is_option(a, &["-machine"])
+                && a.get_property("accel")
+                    .is_some_and(|accel| accel.split(':').any(|a| a == "kvm"))
+        });
+        if !required && !preferred {
+            return;
+        }
+
+        let subject = self.kvm_device.display().to_string();
+        let problem = match std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&self.kvm_device)
+        {
+            Ok(_) => {
+                report.push(Kvm, Passed, subject, "KVM is available");
+                return;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                "does not exist (no hardware virtualization or the kvm module is not loaded)"
+                    .to_string()
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                "is not accessible (is the user in the kvm group?)".to_string()
+            }
+            Err(e) => format!("cannot be opened: {}", e),
+        };
+
+        if !required {
+            report.push(
+                Kvm,
+                Warning,
+                subject,
+                format!("{}; QEMU will fall back to the next accelerator", problem),
+            );
+        } else if self.accel_fallback {
+            report.push(
+                Kvm,
+                Warning,
+                subject,
+                format!("{}; falling back to TCG", problem),
+            );
+            report.set_adjusted_args(kvm_to_tcg(args));
+        } else {
+            report.push(Kvm, Failed, subject, problem);
+        }
+    }
+}
+
+fn is_option(arg: &QemuArg, names: &[&str]) -> bool {
+    names.contains(&canonical_option_name(arg.key()))
+}
+
+/// The `key=value` / bare items of an options-style argument.
+fn opts_items(arg: &QemuArg) -> Vec<String> {
+    match arg {
+        QemuArg::List(_, items) => items.clone(),
+        QemuArg::KeyValue(key, value) => match QemuArg::from_opts(key.clone(), value) {
+            QemuArg::List(_, items) => items,
+            _ => vec![value.clone()],
+        },
+        QemuArg::Flag(_) | QemuArg::Json(..) => Vec::new(),
+    }
+}
+
+/// A boolean property given as `name=on` (or a bare `name`, as QEMU also accepts).
+fn is_on(arg: &QemuArg, name: &str) -> bool {
+    match arg.get_property(name).as_deref() {
+        Some(value) => matches!(value, "on" | "yes" | "true" | "y"),
+        None => opts_items(arg).iter().any(|item| item == name),
+    }
+}
+
+fn requires_kvm(arg: &QemuArg) -> bool {
+    if is_option(arg, &["-enable-kvm"]) {
+        return true;
+    }
+    is_option(arg, &["-accel", "-machine"]) && arg.get_property("accel").as_deref() == Some("kvm")
+}
+
+fn kvm_to_tcg(args: &QemuLaunchArgs) -> QemuLaunchArgs {
+    let mut adjusted = args.clone();
+    adjusted
+        .get_mut_args()
+        .retain(|a| !is_option(a, &["-enable-kvm"]));
+    for arg in adjusted.get_mut_args() {
+        if is_option(arg, &["-accel"]) && requires_kvm(arg) {
+            *arg = QemuArg::from_key_value(arg.key(), "tcg");
+        } else if is_option(arg, &["-machine"]) && requires_kvm(arg) {
+            let items = opts_items(arg)
+                .into_iter()
+                .map(|item| {
+                    if item == "accel=kvm" {
+                        "accel=tcg".to_string()
+                    } else {
+                        item
+                    }
+                })
+                .collect();
+            *arg = QemuArg::from_list(arg.key(), items);
+        } else if is_option(arg, &["-cpu"]) {
+            // `host` only exists with KVM; `max` is TCG's closest equivalent.
+            let mut items = opts_items(arg);
+            if items.first().is_some_and(|model| model == "host") {
+                items[0] = "max".to_string();
+                *arg = match items.len() {
+                    1 => QemuArg::from_key_value(arg.key(), items.remove(0)),
+                    _ => QemuArg::from_list(arg.key(), items),
+                };
+            }
+        }
+    }
+    adjusted
+}
+
+fn check_nested(args: &QemuLaunchArgs, report: &mut QemuPreflightReport) {
+    let Some((module, value)) = ["kvm_intel", "kvm_amd"].iter().find_map(|module| {
+        let path = format!("/sys/module/{}/parameters/nested", module);
+        std::fs::read_to_string(path).ok().map(|v| (*module, v))
+    }) else {
+        return;
+    };
+    let enabled = matches!(value.trim(), "Y" | "y" | "1");
+    let wants_nested = args
+        .get_args()
+        .iter()
+        .filter(|a| is_option(a, &["-cpu"]))
+        .flat_map(opts_items)
+        .any(|item| matches!(item.as_str(), "+vmx" | "+svm" | "vmx=on" | "svm=on"));
+    let (status, message) = match (enabled, wants_nested) {
+        (true, _) => (Passed, "nested virtualization is enabled"),
+        (false, true) => (
+            Warning,
+            "nested virtualization is disabled but the CPU model asks for vmx/svm",
+        ),
+        (false, false) => (Passed, "nested virtualization is disabled"),
+    };
+    report.push(NestedVirtualization, status, module, message);
+}
+
+fn ram_bytes(args: &QemuLaunchArgs) -> u64 {
+    args.get_args()
+        .iter()
+        .rfind(|a| is_option(a, &["-m"]))
+        .and_then(|arg| {
+            let items = opts_items(arg);
+            let size = items
+                .iter()
+                .find_map(|i| i.strip_prefix("size="))
+                .or_else(|| {
+                    items
+                        .first()
+                        .map(String::as_str)
+                        .filter(|i| !i.contains('='))
+                })?;
+            parse_size(size, 20)
+        })
+        .unwrap_or(DEFAULT_RAM_BYTES)
+}
+
+fn read_meminfo_kb(field: &str) -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    meminfo.lines().find_map(|line| {
+        let rest = line.strip_prefix(field)?.strip_prefix(':')?;
+        rest.split_whitespace().next()?.parse().ok()
+    })
+}
+
+/// `(mount point, page size in bytes)` of every hugetlbfs mount.
+fn hugetlbfs_mounts() -> Vec<(PathBuf, Option<u64>)> {
+    let Ok(mounts) = std::fs::read_to_string("/proc/mounts") else {
+        return Vec::new();
+    };
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.get(2) != Some(&"hugetlbfs") {
+                return None;
+            }
+            let pagesize = fields.get(3).and_then(|opts| {
+                opts.split(',')
+                    .find_map(|o| o.strip_prefix("pagesize="))
+                    .and_then(|s| parse_size(s, 0))
+            });
+            Some((PathBuf::from(fields[1]), pagesize))
+        })
+        .collect()
+}
+
+fn check_hugepages(args: &QemuLaunchArgs, report: &mut QemuPreflightReport) {
+    // (subject, bytes needed, page size if known, whether it is hugepage-backed)
+    let mut backings: Vec<(String, u64, Option<u64>)> = Vec::new();
+    let mounts = hugetlbfs_mounts();
+    let mount_pagesize = |path: &str| {
+        mounts
+            .iter()
+            .filter(|(mount, _)| Path::new(path).starts_with(mount))
+            .max_by_key(|(mount, _)| mount.as_os_str().len())
+            .map(|(_, pagesize)| *pagesize)
+    };
+
+    for arg in args.get_args() {
+        if is_option(arg, &["-mem-path"]) {
+            let path = arg.to_args().get(1).cloned().unwrap_or_default();
+            if let Some(pagesize) = mount_pagesize(&path) {
+                backings.push((path, ram_bytes(args), pagesize));
+            }
+        } else if is_option(arg, &["-object"]) {
+            let size = arg
+                .get_property("size")
+                .and_then(|s| parse_size(&s, 0))
+                .unwrap_or(0);
+            match arg.get_property("qom-type").as_deref() {
+                Some("memory-backend-file") => {
+                    let path = arg.get_property("mem-path").unwrap_or_default();
+                    if let Some(pagesize) = mount_pagesize(&path) {
+                        backings.push((path, size, pagesize));
+                    }
+                }
+                Some("memory-backend-memfd") if is_on(arg, "hugetlb") => {
+                    let pagesize = arg
+                        .get_property("hugetlbsize")
+                        .and_then(|s| parse_size(&s, 0));
+                    let id = arg
+                        .get_id()
+                        .unwrap_or_else(|| "memory-backend-memfd".into());
+                    backings.push((id, size, pagesize));
+                }
+                _ => {}
+            }
+        }
+    }
+    if backings.is_empty() {
+        return;
+    }
+
+    let default_pagesize = read_meminfo_kb("Hugepagesize").map(|kb| kb << 10);
+    for (subject, bytes, pagesize) in backings {
+        let Some(pagesize) = pagesize.or(default_pagesize).filter(|p| *p > 0) else {
+            report.push(
+                Hugepages,
+                Failed,
+                subject,
+                "hugepages are not supported on this host",
+            );
+            continue;
+        };
+        let free = std::fs::read_to_string(format!(
+            "/sys/kernel/mm/hugepages/hugepages-{}kB/free_hugepages",
+            pagesize >> 10
+        ))
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok());
+        let needed = bytes.div_ceil(pagesize);
+        match free {
+            None => report.push(
+                Hugepages,
+                Failed,
+                subject,
+                format!("no {} kB hugepage pool on this host", pagesize >> 10),
+            ),
+            Some(free) if free < needed => report.push(
+                Hugepages,
+                Failed,
+                subject,
+                format!(
+                    "needs {} free {} kB hugepages, only {} available",
+                    needed,
+                    pagesize >> 10,
+                    free
+                ),
+            ),
+            Some(free) => report.push(
+                Hugepages,
+                Passed,
+                subject,
+                format!(
+                    "{} of {} free {} kB hugepages needed",
+                    needed,
+                    free,
+                    pagesize >> 10
+                ),
+            ),
+        }
+    }
+}
+
+/// Whether a file name refers to a local path rather than a protocol
+/// (`nbd:`, `https://`, `json:`, `/dev/fdset/...`).
+fn is_local_path(path: &str) -> bool {
+    if path.is_empty() || path.starts_with("/dev/fdset/") {
+        return false;
+    }
+    match path.split_once(':') {
+        Some((prefix, _)) => prefix.contains('/'),
+        None => true,
+    }
+}
+
+fn check_disks(args: &QemuLaunchArgs, report: &mut QemuPreflightReport) {
+    let snapshot = args.get_args().iter().any(|a| is_option(a, &["-snapshot"]));
+    let mut images: Vec<(String, bool)> = Vec::new();
+
+    for arg in args.get_args() {
+        let value = || arg.to_args().get(1).cloned();
+        if is_option(arg, &["-drive"]) {
+            if let Some(file) = arg.get_property("file") {
+                let read_only = snapshot
+                    || is_on(arg, "readonly")
+                    || is_on(arg, "snapshot")
+                    || arg.get_property("media").as_deref() == Some("cdrom");
+                images.push((file, !read_only));
+            }
+        } else if is_option(arg, &["-blockdev"]) {
+            let read_only = is_on(arg, "read-only") || is_on(arg, "auto-read-only");
+            let file = match arg.get_property("driver").as_deref() {
+                Some("file" | "host_device" | "host_cdrom") => arg.get_property("filename"),
+                _ => arg.get_property("file.filename"),
+            };
+            if let Some(file) = file {
+                images.push((file, !read_only));
+            }
+        } else if is_option(
+            arg,
+            &[
+                "-hda",
+                "-hdb",
+                "-hdc",
+                "-hdd",
+                "-fda",
+                "-fdb",
+                "-sd",
+                "-mtdblock",
+                "-pflash",
+            ],
+        ) {
+            images.extend(value().map(|v| (v, !snapshot)));
+        } else if is_option(arg, &["-cdrom", "-kernel", "-initrd", "-bios", "-dtb"]) {
+            images.extend(value().map(|v| (v, false)));
+        }
+    }
+    images.extend(
+        args.get_positionals()
+            .iter()
+            .map(|p| (p.clone(), !snapshot)),
+    );
+
+    for (path, writable) in images {
+        if !is_local_path(&path) {
+            continue;
+        }
+        let (status, message) = check_file_access(Path::new(&path), writable);
+        report.push(DiskImage, status, path, message);
+    }
+}
+
+fn check_file_access(path: &Path, writable: bool) -> (QemuPreflightStatus, String) {
+    if let Err(e) = std::fs::metadata(path) {
+        return (Failed, format!("cannot be accessed: {}", e));
+    }
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return (Failed, "path contains a NUL byte".to_string());
+    };
+    let mode = if writable {
+        libc::R_OK | libc::W_OK
+    } else {
+        libc::R_OK
+    };
+    // SAFETY: `c_path` is a valid NUL-terminated string for the duration of the call.
+    if unsafe { libc::access(c_path.as_ptr(), mode) } == 0 {
+        let what = if writable {
+            "readable and writable"
+        } else {
+            "readable"
+        };
+        return (Passed, what.to_string());
+    }
+    let e = std::io::Error::last_os_error();
+    let message = match e.raw_os_error() {
+        Some(libc::EROFS) => "is on a read-only filesystem but is opened read-write".to_string(),
+        Some(libc::EACCES) if writable => "is not readable and writable".to_string(),
+        Some(libc::EACCES) => "is not readable".to_string(),
+        _ => e.to_string(),
+    };
+    (Failed, message)
+}
+
+/// The Unix socket paths in `args`, each with whether QEMU listens on it.
+pub(crate) fn unix_sockets(args: &QemuLaunchArgs) -> Vec<(String, bool)> {
+    let mut sockets = Vec::new();
+    for arg in args.get_args() {
+        if is_option(arg, &["-chardev"]) {
+            if arg.get_property("backend").as_deref() == Some("socket")
+                && let Some(path) = arg.get_property("path")
+            {
+                sockets.push((path, is_on(arg, "server")));
+            }
+        } else if is_option(arg, &["-qmp", "-monitor", "-serial", "-parallel", "-gdb"])
+            && let Some(value) = arg.to_args().get(1)
+            && let Some(rest) = value.strip_prefix("unix:")
+        {
+            let unix = QemuArg::from_opts("-chardev", rest);
+            let path = opts_items(&unix).first().cloned().unwrap_or_default();
+            let server = is_on(&unix, "server");
+            sockets.push((path.replace(",,", ","), server));
+        }
+    }
+    sockets
+}
+
+fn check_sockets(args: &QemuLaunchArgs, report: &mut QemuPreflightReport) {
+    let sockets = unix_sockets(args);
+    let mut seen: Vec<&str> = Vec::new();
+    for (path, server) in &sockets {
+        if path.is_empty() {
+            continue;
+        }
+        if *server && seen.contains(&path.as_str()) {
+            report.push(
+                Socket,
+                Failed,
+                path,
+                "used by more than one listening socket",
+            );
+            continue;
+        }
+        seen.push(path);
+        let (status, message) = if *server {
+            check_server_socket(Path::new(path))
+        } else {
+            match std::os::unix::net::UnixStream::connect(path) {
+                Ok(_) => (Passed, "accepts connections".to_string()),
+                Err(e) => (Failed, format!("cannot connect: {}", e)),
+            }
+        };
+        report.push(Socket, status, path, message);
+    }
+}
+
+fn check_server_socket(path: &Path) -> (QemuPreflightStatus, String) {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty())
+        && !parent.is_dir()
+    {
+        return (
+            Failed,
+            format!("directory {} does not exist", parent.display()),
+        );
+    }
+    match std::fs::symlink_metadata(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => (Passed, "free".to_string()),
+        Err(e) => (Failed, e.to_string()),
+        Ok(meta) if !meta.file_type().is_socket() => {
+            (Failed, "exists and is not a socket".to_string())
+        }
+        // QEMU unlinks an existing socket file before binding, which would silently
+        // take the path over from a live listener.
+        Ok(_) => match std::os::unix::net::UnixStream::connect(path) {
+            Ok(_) => (Failed, "already in use by a listening process".to_string()),
+            Err(_) => (Warning, "stale socket file will be replaced".to_string()),
+        },
+    }
+}
@@ -0,0 +1,173 @@
+//! Parsers for the text QEMU prints for `-version` and the `help` sub-options.
+
+use super::{QemuDeviceInfo, QemuMachineInfo, QemuVersion};
+
+/// `QEMU emulator version 8.2.2 (Debian 1:8.2.2+ds-0ubuntu1)`
+pub(crate) fn parse_version(output: &str) -> Option<QemuVersion> {
+    let line = output.lines().find(|l| l.contains(" version "))?;
+    let rest = &line[line.find(" version ")? + " version ".len()..];
+    let (number, package) = match rest.split_once(' ') {
+        Some((number, package)) => (number, Some(package.trim())),
+        None => (rest.trim(), None),
+    };
+    let mut parts = number.split('.').map(|p| p.parse::<u32>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().and_then(Result::ok).unwrap_or(0);
+    let micro = parts.next().and_then(Result::ok).unwrap_or(0);
+    let package = package
+        .map(|p| p.trim_start_matches('(').trim_end_matches(')').to_string())
+        .filter(|p| !p.is_empty());
+    Some(QemuVersion {
+        major,
+        minor,
+        micro,
+        package,
+    })
+}
+
+/// `-machine help`: `name   description [(alias of x)] [(default)] [(deprecated)]`
+pub(crate) fn parse_machines(output: &str) -> Vec<QemuMachineInfo> {
+    output
+        .lines()
+        .filter(|l| !l.trim().is_empty() && !l.trim_end().ends_with(':'))
+        .map(|line| {
+            let line = line.trim();
+            let (name, mut description) = match line.split_once(char::is_whitespace) {
+                Some((name, rest)) => (name, rest.trim()),
+                None => (line, ""),
+            };
+            let mut alias_of = None;
+            let mut is_default = false;
+            let mut deprecated = false;
+            while let Some(start) = description.rfind(" (") {
+                let tag = &description[start + 2..];
+                let Some(tag) = tag.strip_suffix(')') else {
+                    break;
+                };
+                if let Some(target) = tag.strip_prefix("alias of ") {
+                    alias_of = Some(target.to_string());
+                } else if tag == "default" {
+                    is_default = true;
+                } else if tag == "deprecated" {
+                    deprecated = true;
+                } else {
+                    break;
+                }
+                description = description[..start].trim_end();
+            }
+            QemuMachineInfo {
+                name: name.to_string(),
+                description: description.to_string(),
+                alias_of,
+                is_default,
+                deprecated,
+            }
+        })
+        .collect()
+}
+
+/// Architectures whose `-cpu help` lines start with an architecture tag.
+const CPU_LINE_PREFIXES: &[&str] = &["x86", "PowerPC", "s390", "Sparc", "MIPS"];
+
+/// `-cpu help`: one model per line until the (x86) feature flag section.
+pub(crate) fn parse_cpus(output: &str) -> Vec<String> {
+    let mut cpus = Vec::new();
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            if !cpus.is_empty() {
+                break;
+            }
+            continue;
+        }
+        if trimmed.ends_with(':') {
+            if !cpus.is_empty() {
+                break;
+            }
+            continue;
+        }
+        let mut words = trimmed.split_whitespace();
+        let Some(mut name) = words.next() else {
+            continue;
+        };
+        if CPU_LINE_PREFIXES.contains(&name) {
+            match words.next() {
+                Some(model) => name = model,
+                None => continue,
+            }
+        }
+        let name = name.trim_matches('\'');
+        if !cpus.iter().any(|c| c == name) {
+            cpus.push(name.to_string());
+        }
+    }
+    cpus
+}
+
+/// `-device help`: `name "virtio-net-pci", bus PCI, alias "virtio-net", desc "..."`
+/// grouped under `<Category> devices:` headers.
+pub(crate) fn parse_devices(output: &str) -> Vec<QemuDeviceInfo> {
+    let mut devices = Vec::new();
+    let mut category = None;
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(header) = line.strip_suffix(':') {
+            category = Some(header.trim_end_matches(" devices").to_string());
+            continue;
+        }
+        let Some(rest) = line.strip_prefix("name ") else {
+            continue;
+        };
+        let fields = split_device_fields(rest);
+        let Some(name) = fields.first().map(|f| unquote(f)) else {
+            continue;
+        };
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find_map(|f| f.strip_prefix(key).map(|v| unquote(v.trim())))
+        };
+        devices.push(QemuDeviceInfo {
+            name,
+            bus: field("bus "),
+            alias: field("alias "),
+            description: field("desc "),
+            category: category.clone(),
+        });
+    }
+    devices
+}
+
+/// Splits on `, ` outside double quotes.
+fn split_device_fields(text: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    let bytes = text.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'"' => in_quotes = !in_quotes,
+            b',' if !in_quotes => {
+                fields.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(text[start..].trim());
+    fields
+}
+
+fn unquote(text: &str) -> String {
+    text.trim().trim_matches('"').to_string()
+}
+
+/// `-accel help`: a header line followed by one accelerator per line.
+pub(crate) fn parse_accels(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.ends_with(':'))
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,13 @@
+mod help_parser;
+mod probe_cache;
+mod qemu_binary;
+mod qemu_capabilities;
+mod qemu_probe_error;
+
+pub use probe_cache::QemuProbeCache;
+pub use qemu_binary::{QemuBinary, discover_qemu_binaries, find_qemu_binary};
+pub use qemu_capabilities::{
+    QemuCapabilities, QemuCapabilityKind, QemuDeviceInfo, QemuMachineInfo, QemuMissingCapability,
+    QemuVersion,
+};
+pub use qemu_probe_error::QemuProbeError;
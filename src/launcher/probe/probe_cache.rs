@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::SystemTime;
+
+use tokio::sync::Mutex;
+
+use super::{QemuCapabilities, QemuProbeError};
+
+#[derive(Debug)]
+struct CacheEntry {
+    modified: Option<SystemTime>,
+    len: u64,
+    capabilities: Arc<QemuCapabilities>,
+}
+
+/// Remembers `QemuCapabilities` per binary and probes again when the file changes
+/// (e.g. after a package upgrade).
+#[derive(Debug, Default)]
+pub struct QemuProbeCache {
+    entries: Mutex<HashMap<PathBuf, CacheEntry>>,
+}
+
+impl QemuProbeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A process-wide cache.
+    pub fn global() -> &'static QemuProbeCache {
+        static GLOBAL: OnceLock<QemuProbeCache> = OnceLock::new();
+        GLOBAL.get_or_init(QemuProbeCache::new)
+    }
+
+    pub async fn get(
+        &self,
+        binary: impl AsRef<Path>,
+    ) -> Result<Arc<QemuCapabilities>, QemuProbeError> {
+        let binary = binary.as_ref();
+        let metadata = tokio::fs::metadata(binary)
+            .await
+            .map_err(|source| QemuProbeError::Io {
+                binary: binary.to_path_buf(),
+                source,
+            })?;
+        let modified = metadata.modified().ok();
+        let len = metadata.len();
+
+        // Held across the probe so concurrent callers wait for one probe.
+        let mut entries = self.entries.lock().await;
+        if let Some(entry) = entries.get(binary)
+            && entry.modified == modified
+            && entry.len == len
+        {
+            return Ok(entry.capabilities.clone());
+        }
+        let capabilities = Arc::new(QemuCapabilities::probe(binary).await?);
+        entries.insert(
+            binary.to_path_buf(),
+            CacheEntry {
+                modified,
+                len,
+                capabilities: capabilities.clone(),
+            },
+        );
+        Ok(capabilities)
+    }
+
+    pub async fn invalidate(&self, binary: impl AsRef<Path>) {
+        self.entries.lock().await.remove(binary.as_ref());
+    }
+
+    pub async fn clear(&self) {
+        self.entries.lock().await.clear();
+    }
+}
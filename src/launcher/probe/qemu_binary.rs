@@ -0,0 +1,75 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// Directories searched in addition to `$PATH`.
+const COMMON_PREFIXES: &[&str] = &[
+    "/usr/bin",
+    "/usr/local/bin",
+    "/usr/libexec",
+    "/opt/homebrew/bin",
+    "/opt/local/bin",
+    "/snap/bin",
+];
+
+/// A `qemu-system-<arch>` (or `qemu-kvm`) executable found on this host.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuBinary {
+    pub path: PathBuf,
+    pub arch: String,
+}
+
+impl QemuBinary {
+    fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        let arch = match name.strip_prefix("qemu-system-") {
+            Some(arch) if !arch.is_empty() => arch.to_string(),
+            // RHEL-style build for the host architecture.
+            _ if name == "qemu-kvm" => std::env::consts::ARCH.to_string(),
+            _ => return None,
+        };
+        let metadata = std::fs::metadata(path).ok()?;
+        if !metadata.is_file() || metadata.permissions().mode() & 0o111 == 0 {
+            return None;
+        }
+        Some(Self {
+            path: path.to_path_buf(),
+            arch,
+        })
+    }
+}
+
+/// Every QEMU system emulator on `$PATH` and in common install prefixes, in search
+/// order. Symlinks to the same file are reported once.
+pub fn discover_qemu_binaries() -> Vec<QemuBinary> {
+    let mut dirs: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default();
+    dirs.extend(COMMON_PREFIXES.iter().map(PathBuf::from));
+
+    let mut seen = Vec::new();
+    let mut binaries = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut found: Vec<QemuBinary> = entries
+            .filter_map(|entry| QemuBinary::from_path(&entry.ok()?.path()))
+            .collect();
+        found.sort_by(|a, b| a.path.cmp(&b.path));
+        for binary in found {
+            let real = std::fs::canonicalize(&binary.path).unwrap_or_else(|_| binary.path.clone());
+            if !seen.contains(&real) {
+                seen.push(real);
+                binaries.push(binary);
+            }
+        }
+    }
+    binaries
+}
+
+/// The first `qemu-system-<arch>` found by `discover_qemu_binaries`.
+pub fn find_qemu_binary(arch: &str) -> Option<QemuBinary> {
+    discover_qemu_binaries()
+        .into_iter()
+        .find(|b| b.arch == arch)
+}
@@ -0,0 +1,263 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use super::QemuProbeError;
+use super::help_parser::{parse_accels, parse_cpus, parse_devices, parse_machines, parse_version};
+use crate::launcher::QemuLaunchArgs;
+use crate::launcher::qemu_args::canonical_option_name;
+
+/// How long a single `-version` / `help` invocation may take.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct QemuVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub micro: u32,
+    /// Distribution package string, e.g. `Debian 1:8.2.2+ds-0ubuntu1`.
+    pub package: Option<String>,
+}
+
+impl QemuVersion {
+    pub fn at_least(&self, major: u32, minor: u32) -> bool {
+        (self.major, self.minor) >= (major, minor)
+    }
+}
+
+impl std::fmt::Display for QemuVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.micro)
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QemuMachineInfo {
+    pub name: String,
+    pub description: String,
+    pub alias_of: Option<String>,
+    pub is_default: bool,
+    pub deprecated: bool,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QemuDeviceInfo {
+    pub name: String,
+    pub bus: Option<String>,
+    pub alias: Option<String>,
+    pub description: Option<String>,
+    /// The `-device help` section, e.g. `Network` or `Storage`.
+    pub category: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum QemuCapabilityKind {
+    Machine,
+    Cpu,
+    Device,
+    Accel,
+}
+
+/// Something `QemuLaunchArgs` asks for that the probed binary does not provide.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuMissingCapability {
+    pub kind: QemuCapabilityKind,
+    pub name: String,
+}
+
+impl std::fmt::Display for QemuMissingCapability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            QemuCapabilityKind::Machine => "machine type",
+            QemuCapabilityKind::Cpu => "CPU model",
+            QemuCapabilityKind::Device => "device",
+            QemuCapabilityKind::Accel => "accelerator",
+        };
+        write!(f, "QEMU does not support {} '{}'", kind, self.name)
+    }
+}
+
+/// What a QEMU binary supports, as reported by `-version` and the `help` sub-options.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QemuCapabilities {
+    pub binary: PathBuf,
+    pub version: QemuVersion,
+    pub machines: Vec<QemuMachineInfo>,
+    pub cpus: Vec<String>,
+    pub devices: Vec<QemuDeviceInfo>,
+    pub accels: Vec<String>,
+}
+
+impl QemuCapabilities {
+    /// Runs `binary` with `-version`, `-machine help`, `-cpu help`, `-device help` and
+    /// `-accel help`. Only `-version` is required to succeed; a help query the binary
+    /// rejects leaves that list empty.
+    pub async fn probe(binary: impl AsRef<Path>) -> Result<Self, QemuProbeError> {
+        let binary = binary.as_ref();
+        let version_output = run_query(binary, &["-version"]).await?;
+        let version = parse_version(&version_output)
+            .ok_or_else(|| QemuProbeError::UnrecognizedVersion(version_output.clone()))?;
+
+        let (machines, cpus, devices, accels) = tokio::join!(
+            run_query(binary, &["-machine", "help"]),
+            run_query(binary, &["-cpu", "help"]),
+            run_query(binary, &["-device", "help"]),
+            run_query(binary, &["-accel", "help"]),
+        );
+        let optional = |result: Result<String, QemuProbeError>| match result {
+            Ok(output) => output,
+            Err(e) => {
+                log::warn!("QemuCapabilities: {}", e);
+                String::new()
+            }
+        };
+
+        Ok(Self {
+            binary: binary.to_path_buf(),
+            version,
+            machines: parse_machines(&optional(machines)),
+            cpus: parse_cpus(&optional(cpus)),
+            devices: parse_devices(&optional(devices)),
+            accels: parse_accels(&optional(accels)),
+        })
+    }
+
+    pub fn has_machine(&self, name: &str) -> bool {
+        self.machines.iter().any(|m| m.name == name)
+    }
+
+    pub fn default_machine(&self) -> Option<&QemuMachineInfo> {
+        self.machines.iter().find(|m| m.is_default)
+    }
+
+    pub fn has_cpu(&self, name: &str) -> bool {
+        self.cpus.iter().any(|c| c == name)
+    }
+
+    pub fn has_device(&self, name: &str) -> bool {
+        self.devices
+            .iter()
+            .any(|d| d.name == name || d.alias.as_deref() == Some(name))
+    }
+
+    pub fn has_accel(&self, name: &str) -> bool {
+        self.accels.iter().any(|a| a == name)
+    }
+
+    /// Checks the machine type, CPU model, devices and accelerators `args` refer to.
+    /// Lists that could not be probed are not checked.
+    pub fn check(&self, args: &QemuLaunchArgs) -> Result<(), Vec<QemuMissingCapability>> {
+        let mut missing = Vec::new();
+        let mut report = |kind, name: String| {
+            if !missing
+                .iter()
+                .any(|m: &QemuMissingCapability| m.kind == kind && m.name == name)
+            {
+                missing.push(QemuMissingCapability { kind, name });
+            }
+        };
+
+        for arg in args.get_args() {
+            match canonical_option_name(arg.key()) {
+                "-machine" => {
+                    if let Some(machine) = arg.get_property("type")
+                        && !self.machines.is_empty()
+                        && machine != "help"
+                        && !self.has_machine(&machine)
+                    {
+                        report(QemuCapabilityKind::Machine, machine);
+                    }
+                    if let Some(accels) = arg.get_property("accel") {
+                        self.check_accels(accels.split(':'), &mut report);
+                    }
+                }
+                "-cpu" => {
+                    let model = arg
+                        .to_args()
+                        .get(1)
+                        .and_then(|v| v.split(',').next().map(str::to_string));
+                    if let Some(model) = model
+                        && !self.cpus.is_empty()
+                        && model != "help"
+                        && !self.has_cpu(&model)
+                    {
+                        report(QemuCapabilityKind::Cpu, model);
+                    }
+                }
+                "-device" => {
+                    if let Some(driver) = arg.get_property("driver")
+                        && !self.devices.is_empty()
+                        && driver != "help"
+                        && !self.has_device(&driver)
+                    {
+                        report(QemuCapabilityKind::Device, driver);
+                    }
+                }
+                "-accel" => {
+                    if let Some(accel) = arg.get_property("accel") {
+                        self.check_accels(std::iter::once(accel.as_str()), &mut report);
+                    }
+                }
+                "-enable-kvm" => self.check_accels(std::iter::once("kvm"), &mut report),
+                _ => {}
+            }
+        }
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(missing)
+        }
+    }
+
+    /// QEMU falls back through `accel=a:b:c`, so only report when none is available.
+    fn check_accels<'a>(
+        &self,
+        accels: impl Iterator<Item = &'a str>,
+        report: &mut impl FnMut(QemuCapabilityKind, String),
+    ) {
+        if self.accels.is_empty() {
+            return;
+        }
+        let accels: Vec<&str> = accels.filter(|a| !a.is_empty()).collect();
+        if !accels.is_empty() && !accels.iter().any(|a| self.has_accel(a)) {
+            report(QemuCapabilityKind::Accel, accels.join(":"));
+        }
+    }
+}
+
+async fn run_query(binary: &Path, args: &[&str]) -> Result<String, QemuProbeError> {
+    let owned_args = || args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    let output = Command::new(binary)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = match tokio::time::timeout(PROBE_TIMEOUT, output).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(source)) => {
+            return Err(QemuProbeError::Io {
+                binary: binary.to_path_buf(),
+                source,
+            });
+        }
+        Err(_) => {
+            return Err(QemuProbeError::Timeout {
+                binary: binary.to_path_buf(),
+                args: owned_args(),
+            });
+        }
+    };
+    if !output.status.success() {
+        return Err(QemuProbeError::Failed {
+            binary: binary.to_path_buf(),
+            args: owned_args(),
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum QemuProbeError {
+    Io {
+        binary: PathBuf,
+        source: std::io::Error,
+    },
+    Timeout {
+        binary: PathBuf,
+        args: Vec<String>,
+    },
+    Failed {
+        binary: PathBuf,
+        args: Vec<String>,
+        status: std::process::ExitStatus,
+        stderr: String,
+    },
+    UnrecognizedVersion(String),
+}
+
+impl std::fmt::Display for QemuProbeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QemuProbeError::Io { binary, source } => {
+                write!(f, "Cannot run {}: {}", binary.display(), source)
+            }
+            QemuProbeError::Timeout { binary, args } => {
+                write!(f, "{} {} timed out", binary.display(), args.join(" "))
+            }
+            QemuProbeError::Failed {
+                binary,
+                args,
+                status,
+                stderr,
+            } => write!(
+                f,
+                "{} {} failed ({}): {}",
+                binary.display(),
+                args.join(" "),
+                status,
+                stderr.trim()
+            ),
+            QemuProbeError::UnrecognizedVersion(output) => {
+                write!(f, "Unrecognized -version output: {:?}", output)
+            }
+        }
+    }
+}
+
+impl std::error::Error for QemuProbeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QemuProbeError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
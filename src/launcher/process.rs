@@ -1,76 +1,328 @@
-use std::process::Stdio;
-use tokio::io::AsyncReadExt;
+use std::time::Duration;
 use tokio::process::Command;
 
-use super::QemuLaunchArgs;
+use super::adopted_process::AdoptedProcess;
+use super::spawn::configure_command;
+use super::{
+    QemuCgroup, QemuLaunchArgs, QemuLaunchOptions, QemuOutput, QemuOutputSource, QemuStdio,
+};
+
+const DEFAULT_TERM_GRACE: Duration = Duration::from_secs(10);
+
+/// Lines of captured stderr included when a `-daemonize` launch fails.
+const DAEMONIZE_STDERR_TAIL_LINES: usize = 20;
+
+#[derive(Debug)]
+enum ProcessHandle {
+    Child(tokio::process::Child),
+    Adopted(AdoptedProcess),
+}
 
 #[derive(Debug)]
 pub struct QemuProcess {
-    child: tokio::process::Child,
+    handle: ProcessHandle,
+    output: Option<QemuOutput>,
+    cgroup: Option<QemuCgroup>,
 }
 
 impl QemuProcess {
     pub async fn launch(args: &QemuLaunchArgs) -> std::io::Result<Self> {
-        let mut cmd = Command::new(args.get_binary());
+        Self::launch_with_options(args, &QemuLaunchOptions::default()).await
+    }
+
+    pub async fn launch_with_options(
+        args: &QemuLaunchArgs,
+        options: &QemuLaunchOptions,
+    ) -> std::io::Result<Self> {
+        let argv = launch_argv(args, options);
+        let mut cmd = Command::new(&argv[0]);
+        cmd.args(&argv[1..]);
+        cmd.stdin(std::process::Stdio::null())
+            .stdout(options.get_stdout().to_stdio()?)
+            .stderr(options.get_stderr().to_stdio()?);
+
+        let mut cgroup = None;
+        if let Some(config) = options.get_cgroup() {
+            match QemuCgroup::create(config).await {
+                Ok(created) => {
+                    for reason in created.skipped() {
+                        log::warn!("QemuProcess: {}: {}", created.path().display(), reason);
+                    }
+                    cgroup = Some(created);
+                }
+                Err(e) if config.is_required() => return Err(e),
+                Err(e) => log::warn!("QemuProcess: running QEMU without a cgroup: {}", e),
+            }
+        }
+        let cgroup_procs = cgroup.as_ref().map(QemuCgroup::open_procs).transpose()?;
+        configure_command(&mut cmd, options, cgroup_procs)?;
+
+        // If QEMU cannot join the cgroup, the spawn fails and dropping it removes it.
+        let mut child = cmd.spawn().map_err(|e| match &cgroup {
+            Some(cgroup) => std::io::Error::new(
+                e.kind(),
+                format!("cannot start QEMU in {}: {}", cgroup.path().display(), e),
+            ),
+            None => e,
+        })?;
+
+        let mut output = None;
+        if let QemuStdio::Pipe(log) = options.get_stdout()
+            && let Some(stdout) = child.stdout.take()
+        {
+            output
+                .get_or_insert_with(|| QemuOutput::new(options.get_output_buffer_lines()))
+                .attach(stdout, QemuOutputSource::Stdout, log.clone());
+        }
+        if let QemuStdio::Pipe(log) = options.get_stderr()
+            && let Some(stderr) = child.stderr.take()
+        {
+            output
+                .get_or_insert_with(|| QemuOutput::new(options.get_output_buffer_lines()))
+                .attach(stderr, QemuOutputSource::Stderr, log.clone());
+        }
+
+        let mut process = Self {
+            handle: ProcessHandle::Child(child),
+            output,
+            cgroup,
+        };
+        if args.is_daemonized() {
+            process.follow_daemon(args).await?;
+        }
+        Ok(process)
+    }
 
-        for arg in args.get_args() {
-            cmd.args(arg.to_args());
+    /// Takes over a QEMU process that this process did not spawn (see `AdoptedProcess`
+    /// for what can and cannot be observed about it).
+    pub fn adopt(pid: u32) -> std::io::Result<Self> {
+        Ok(Self {
+            handle: ProcessHandle::Adopted(AdoptedProcess::new(pid)?),
+            output: None,
+            cgroup: None,
+        })
+    }
+
+    /// With `-daemonize` the spawned process exits once the guest is set up; wait for
+    /// that, then adopt the daemon through its `-pidfile`.
+    async fn follow_daemon(&mut self, args: &QemuLaunchArgs) -> std::io::Result<()> {
+        let Some(pidfile) = args.get_pidfile() else {
+            let _ = self.kill().await;
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "-daemonize requires -pidfile to keep track of QEMU",
+            ));
+        };
+        // The daemon inherits the cgroup, so wait without tearing it down.
+        let status = self.wait_handle().await?;
+        if !status.success() {
+            let mut message = format!("QEMU failed to daemonize: {}", status);
+            for line in self.stderr_tail(DAEMONIZE_STDERR_TAIL_LINES).await {
+                message.push('\n');
+                message.push_str(&line);
+            }
+            return Err(std::io::Error::other(message));
+        }
+        let pid = read_pidfile(pidfile).await?;
+        self.handle = ProcessHandle::Adopted(AdoptedProcess::new(pid)?);
+        Ok(())
+    }
+
+    /// `None` for adopted processes.
+    pub fn get_child(&self) -> Option<&tokio::process::Child> {
+        match &self.handle {
+            ProcessHandle::Child(child) => Some(child),
+            ProcessHandle::Adopted(_) => None,
         }
+    }
 
-        cmd.args(args.get_positionals());
-        cmd.stdin(Stdio::null())
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit());
+    pub fn get_mut_child(&mut self) -> Option<&mut tokio::process::Child> {
+        match &mut self.handle {
+            ProcessHandle::Child(child) => Some(child),
+            ProcessHandle::Adopted(_) => None,
+        }
+    }
 
-        let child = cmd.spawn()?;
-        Ok(Self { child })
+    /// Whether this process was adopted (via `adopt` or `-daemonize`) rather than spawned.
+    pub fn is_adopted(&self) -> bool {
+        matches!(self.handle, ProcessHandle::Adopted(_))
     }
 
-    pub fn get_child(&self) -> &tokio::process::Child {
-        &self.child
+    /// Captured output, present when stdout or stderr was launched with `QemuStdio::Pipe`.
+    pub fn get_output(&self) -> Option<&QemuOutput> {
+        self.output.as_ref()
     }
 
-    pub fn get_mut_child(&mut self) -> &mut tokio::process::Child {
-        &mut self.child
+    pub fn get_mut_output(&mut self) -> Option<&mut QemuOutput> {
+        self.output.as_mut()
     }
 
+    /// Waits for QEMU to exit and removes its cgroup, if any. For an adopted process
+    /// the status carries neither an exit code nor a signal, as it cannot be known.
     pub async fn wait(&mut self) -> std::io::Result<std::process::ExitStatus> {
-        self.child.wait().await
+        let status = self.wait_handle().await?;
+        self.release_cgroup().await;
+        Ok(status)
+    }
+
+    async fn wait_handle(&mut self) -> std::io::Result<std::process::ExitStatus> {
+        match &mut self.handle {
+            ProcessHandle::Child(child) => child.wait().await,
+            ProcessHandle::Adopted(process) => process.wait().await,
+        }
+    }
+
+    async fn release_cgroup(&mut self) {
+        if let Some(cgroup) = &mut self.cgroup
+            && let Err(e) = cgroup.remove().await
+        {
+            log::warn!(
+                "QemuProcess: cannot remove {}: {}",
+                cgroup.path().display(),
+                e
+            );
+        }
+    }
+
+    /// The cgroup QEMU was placed in, if `QemuLaunchOptions::with_cgroup` was used
+    /// and it could be set up.
+    pub fn get_cgroup(&self) -> Option<&QemuCgroup> {
+        self.cgroup.as_ref()
     }
 
+    fn try_wait(&mut self) -> std::io::Result<Option<std::process::ExitStatus>> {
+        match &mut self.handle {
+            ProcessHandle::Child(child) => child.try_wait(),
+            ProcessHandle::Adopted(process) => process.try_wait(),
+        }
+    }
+
+    /// Sends SIGTERM and waits up to `DEFAULT_TERM_GRACE` for QEMU to exit before
+    /// falling back to SIGKILL.
     pub async fn terminate(&mut self) -> std::io::Result<()> {
-        self.child.kill().await
+        self.terminate_with_grace(DEFAULT_TERM_GRACE).await
+    }
+
+    pub async fn terminate_with_grace(&mut self, grace: Duration) -> std::io::Result<()> {
+        if self.try_wait()?.is_some() {
+            return Ok(());
+        }
+        self.send_signal(libc::SIGTERM)?;
+        match tokio::time::timeout(grace, self.wait()).await {
+            Ok(status) => status.map(|_| ()),
+            Err(_) => self.kill().await,
+        }
+    }
+
+    /// SIGKILL, without giving QEMU a chance to clean up.
+    pub async fn kill(&mut self) -> std::io::Result<()> {
+        match &mut self.handle {
+            ProcessHandle::Child(child) => {
+                child.kill().await?;
+                self.release_cgroup().await;
+                Ok(())
+            }
+            ProcessHandle::Adopted(process) => {
+                if process.try_wait()?.is_none() {
+                    self.send_signal(libc::SIGKILL)?;
+                }
+                self.wait().await.map(|_| ())
+            }
+        }
+    }
+
+    pub fn send_signal(&self, signal: i32) -> std::io::Result<()> {
+        let Some(pid) = self.pid() else {
+            return Ok(());
+        };
+        // SAFETY: kill(2) has no memory-safety preconditions.
+        if unsafe { libc::kill(pid as libc::pid_t, signal) } == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
     }
 
     pub fn is_running(&mut self) -> bool {
-        self.child.try_wait().unwrap_or(None).is_none()
+        self.try_wait().unwrap_or(None).is_none()
     }
 
     pub fn pid(&self) -> Option<u32> {
-        self.child.id()
+        match &self.handle {
+            ProcessHandle::Child(child) => child.id(),
+            ProcessHandle::Adopted(process) if !process.is_exited() => Some(process.pid()),
+            ProcessHandle::Adopted(_) => None,
+        }
     }
 
     pub fn try_wait_exit_code(&mut self) -> Option<i32> {
-        self.child.try_wait().ok().flatten().and_then(|s| s.code())
+        self.try_wait().ok().flatten().and_then(|s| s.code())
     }
 
-    pub async fn read_stdout(&mut self) -> std::io::Result<Option<String>> {
-        if let Some(stdout) = &mut self.child.stdout {
-            let mut buf = String::new();
-            stdout.read_to_string(&mut buf).await?;
-            Ok(Some(buf))
-        } else {
-            Ok(None)
+    /// The full exit status (including a terminating signal) if QEMU has exited.
+    pub fn try_wait_status(&mut self) -> Option<std::process::ExitStatus> {
+        self.try_wait().ok().flatten()
+    }
+
+    /// The last `count` captured stderr lines. If QEMU has already exited, this first
+    /// gives the readers a moment to drain what is left in the pipe.
+    pub async fn stderr_tail(&mut self, count: usize) -> Vec<String> {
+        let exited = matches!(self.try_wait(), Ok(Some(_)));
+        match &mut self.output {
+            Some(output) => {
+                if exited {
+                    let _ = tokio::time::timeout(Duration::from_millis(500), output.wait_closed())
+                        .await;
+                }
+                output.tail(Some(QemuOutputSource::Stderr), count)
+            }
+            None => Vec::new(),
         }
     }
 
+    /// Captured stdout so far (from the ring buffer), or `None` if stdout is not piped.
+    pub async fn read_stdout(&mut self) -> std::io::Result<Option<String>> {
+        Ok(self.read_captured(QemuOutputSource::Stdout))
+    }
+
+    /// Captured stderr so far (from the ring buffer), or `None` if stderr is not piped.
     pub async fn read_stderr(&mut self) -> std::io::Result<Option<String>> {
-        if let Some(stderr) = &mut self.child.stderr {
-            let mut buf = String::new();
-            stderr.read_to_string(&mut buf).await?;
-            Ok(Some(buf))
-        } else {
-            Ok(None)
+        Ok(self.read_captured(QemuOutputSource::Stderr))
+    }
+
+    fn read_captured(&self, source: QemuOutputSource) -> Option<String> {
+        let output = self.output.as_ref().filter(|o| o.captures(source))?;
+        let mut buf = String::new();
+        for line in output.snapshot().iter().filter(|l| l.source == source) {
+            buf.push_str(&line.line);
+            buf.push('\n');
         }
+        Some(buf)
+    }
+}
+
+/// The argv `QemuProcess::launch_with_options` executes: the binary, the `-add-fd`
+/// arguments for passed descriptors, the launch args and the positionals.
+pub(crate) fn launch_argv(args: &QemuLaunchArgs, options: &QemuLaunchOptions) -> Vec<String> {
+    let mut argv = vec![args.get_binary().to_string()];
+    for arg in options.add_fd_args() {
+        argv.extend(arg.to_args());
     }
+    for arg in args.get_args() {
+        argv.extend(arg.to_args());
+    }
+    argv.extend(args.get_positionals().iter().cloned());
+    argv
+}
+
+/// Reads the PID QEMU wrote with `-pidfile`.
+pub async fn read_pidfile(path: impl AsRef<std::path::Path>) -> std::io::Result<u32> {
+    let path = path.as_ref();
+    let text = tokio::fs::read_to_string(path).await?;
+    text.trim().parse().map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{}: not a PID: {:?}", path.display(), text.trim()),
+        )
+    })
 }
@@ -0,0 +1,395 @@
+use serde_json::{Value, json};
+
+use super::option_table::{canonical_option_name, parse_size};
+use super::{QemuArg, QemuLaunchArgs};
+use crate::qmp::commands::QmpCommand;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum QemuChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// How a change can be applied to a running VM.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QemuChangeApply {
+    /// Through QMP (hotplug, unplug or balloon), without restarting QEMU.
+    Live,
+    /// Only by restarting QEMU with the new args.
+    Cold,
+}
+
+/// One difference found by `QemuLaunchArgs::diff`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuArgChange {
+    kind: QemuChangeKind,
+    apply: QemuChangeApply,
+    /// The option, with its id if it has one, e.g. `-device net0`.
+    subject: String,
+    before: Vec<String>,
+    after: Vec<String>,
+    commands: Vec<QmpCommand>,
+}
+
+impl QemuArgChange {
+    pub fn get_kind(&self) -> QemuChangeKind {
+        self.kind
+    }
+
+    pub fn get_apply(&self) -> QemuChangeApply {
+        self.apply
+    }
+
+    pub fn get_subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// The old tokens; empty for an added argument.
+    pub fn get_before(&self) -> &[String] {
+        &self.before
+    }
+
+    /// The new tokens; empty for a removed argument.
+    pub fn get_after(&self) -> &[String] {
+        &self.after
+    }
+
+    /// The QMP commands that apply a live change; empty for cold ones.
+    pub fn get_commands(&self) -> &[QmpCommand] {
+        &self.commands
+    }
+}
+
+impl std::fmt::Display for QemuArgChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            QemuChangeKind::Added => "added",
+            QemuChangeKind::Removed => "removed",
+            QemuChangeKind::Modified => "modified",
+        };
+        let apply = match self.apply {
+            QemuChangeApply::Live => "live",
+            QemuChangeApply::Cold => "restart",
+        };
+        write!(f, "[{}] {} {}", apply, kind, self.subject)?;
+        match self.kind {
+            QemuChangeKind::Added => write!(f, ": {}", self.after.join(" ")),
+            QemuChangeKind::Removed => write!(f, ": {}", self.before.join(" ")),
+            QemuChangeKind::Modified => {
+                write!(f, ": {} -> {}", self.before.join(" "), self.after.join(" "))
+            }
+        }
+    }
+}
+
+/// Result of `QemuLaunchArgs::diff`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QemuArgsDiff {
+    changes: Vec<QemuArgChange>,
+}
+
+impl QemuArgsDiff {
+    pub fn get_changes(&self) -> &[QemuArgChange] {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Whether any change can only be applied by restarting QEMU.
+    pub fn needs_restart(&self) -> bool {
+        self.changes
+            .iter()
+            .any(|c| c.apply == QemuChangeApply::Cold)
+    }
+
+    /// The QMP commands for all live changes, ordered so that devices are unplugged
+    /// before their backends and backends are plugged in before their devices.
+    pub fn live_commands(&self) -> Vec<QmpCommand> {
+        let rank = |change: &QemuArgChange| {
+            let device = change.subject.starts_with("-device ");
+            match change.kind {
+                QemuChangeKind::Removed if device => 0,
+                QemuChangeKind::Removed => 1,
+                QemuChangeKind::Added if !device => 2,
+                QemuChangeKind::Added => 3,
+                QemuChangeKind::Modified => 4,
+            }
+        };
+        let mut live: Vec<&QemuArgChange> = self
+            .changes
+            .iter()
+            .filter(|c| c.apply == QemuChangeApply::Live)
+            .collect();
+        live.sort_by_key(|c| rank(c));
+        live.into_iter()
+            .flat_map(|c| c.commands.iter().cloned())
+            .collect()
+    }
+}
+
+/// Lists the changes one per line.
+impl std::fmt::Display for QemuArgsDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.changes.is_empty() {
+            return write!(f, "no changes");
+        }
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn diff(before: &QemuLaunchArgs, after: &QemuLaunchArgs) -> QemuArgsDiff {
+    let mut changes = Vec::new();
+    if before.get_binary() != after.get_binary() {
+        changes.push(cold(
+            QemuChangeKind::Modified,
+            "binary",
+            vec![before.get_binary().to_string()],
+            vec![after.get_binary().to_string()],
+        ));
+    }
+
+    // Arguments with an id are matched by option and id, wherever they are.
+    let old_keyed = keyed(before);
+    let new_keyed = keyed(after);
+    for (subject, old) in &old_keyed {
+        match new_keyed.iter().find(|(s, _)| s == subject) {
+            Some((_, new)) if normalized(old) != normalized(new) => {
+                changes.push(cold(
+                    QemuChangeKind::Modified,
+                    subject,
+                    old.to_args(),
+                    new.to_args(),
+                ));
+            }
+            Some(_) => {}
+            None => changes.push(plug(QemuChangeKind::Removed, subject, old)),
+        }
+    }
+    for (subject, new) in &new_keyed {
+        if !old_keyed.iter().any(|(s, _)| s == subject) {
+            changes.push(plug(QemuChangeKind::Added, subject, new));
+        }
+    }
+    require_live_backends(&mut changes, &old_keyed, &new_keyed);
+
+    // The rest are matched per option: a single occurrence on each side is a
+    // modification, otherwise occurrences are paired up by value.
+    let unkeyed = |args: &QemuLaunchArgs| -> Vec<QemuArg> {
+        args.get_args()
+            .iter()
+            .filter(|a| a.get_id().is_none())
+            .cloned()
+            .collect()
+    };
+    let old_unkeyed = unkeyed(before);
+    let new_unkeyed = unkeyed(after);
+    let mut options: Vec<&str> = Vec::new();
+    for arg in old_unkeyed.iter().chain(&new_unkeyed) {
+        if !options.contains(&canonical_option_name(arg.key())) {
+            options.push(canonical_option_name(arg.key()));
+        }
+    }
+    for option in options {
+        let of = |args: &[QemuArg]| -> Vec<QemuArg> {
+            args.iter()
+                .filter(|a| canonical_option_name(a.key()) == option)
+                .cloned()
+                .collect()
+        };
+        let mut old = of(&old_unkeyed);
+        let mut new = of(&new_unkeyed);
+        if let ([old], [new]) = (old.as_slice(), new.as_slice()) {
+            if normalized(old) != normalized(new) {
+                changes.push(modified(option, old, new, after));
+            }
+            continue;
+        }
+        old.retain(
+            |o| match new.iter().position(|n| normalized(n) == normalized(o)) {
+                Some(index) => {
+                    new.remove(index);
+                    false
+                }
+                None => true,
+            },
+        );
+        for arg in old {
+            changes.push(cold(
+                QemuChangeKind::Removed,
+                option,
+                arg.to_args(),
+                Vec::new(),
+            ));
+        }
+        for arg in new {
+            changes.push(cold(
+                QemuChangeKind::Added,
+                option,
+                Vec::new(),
+                arg.to_args(),
+            ));
+        }
+    }
+
+    if before.get_positionals() != after.get_positionals() {
+        changes.push(cold(
+            QemuChangeKind::Modified,
+            "positionals",
+            before.get_positionals().clone(),
+            after.get_positionals().clone(),
+        ));
+    }
+    QemuArgsDiff { changes }
+}
+
+/// The arguments that have an id, labelled with their option and id.
+fn keyed(args: &QemuLaunchArgs) -> Vec<(String, &QemuArg)> {
+    args.get_args()
+        .iter()
+        .filter_map(|a| {
+            a.get_id()
+                .map(|id| (format!("{} {}", canonical_option_name(a.key()), id), a))
+        })
+        .collect()
+}
+
+/// Makes `-device a,id=x` and `-device '{"driver":"a","id":"x"}'` compare equal.
+fn normalized(arg: &QemuArg) -> Result<Value, Vec<String>> {
+    match arg.to_json_syntax() {
+        Some(QemuArg::Json(_, value)) => Ok(value),
+        _ => Err(arg.to_args()),
+    }
+}
+
+fn cold(
+    kind: QemuChangeKind,
+    subject: &str,
+    before: Vec<String>,
+    after: Vec<String>,
+) -> QemuArgChange {
+    QemuArgChange {
+        kind,
+        apply: QemuChangeApply::Cold,
+        subject: subject.to_string(),
+        before,
+        after,
+        commands: Vec::new(),
+    }
+}
+
+/// Backend references of `-device` and the options (by subject) they can point to.
+const BACKEND_PROPERTIES: &[(&str, &[&str])] = &[
+    ("netdev", &["-netdev"]),
+    ("drive", &["-blockdev", "-drive"]),
+    ("chardev", &["-chardev"]),
+];
+
+/// Makes an added device cold when a backend it refers to is not there in the running
+/// VM already and is not hotplugged along with it (missing, changed or cold itself).
+fn require_live_backends(
+    changes: &mut [QemuArgChange],
+    old_keyed: &[(String, &QemuArg)],
+    new_keyed: &[(String, &QemuArg)],
+) {
+    let live_backend = |changes: &[QemuArgChange], subject: &str| {
+        let unchanged = old_keyed.iter().any(|(s, _)| s == subject)
+            && new_keyed.iter().any(|(s, _)| s == subject)
+            && !changes.iter().any(|c| c.subject == subject);
+        unchanged
+            || changes.iter().any(|c| {
+                c.subject == subject
+                    && c.kind == QemuChangeKind::Added
+                    && c.apply == QemuChangeApply::Live
+            })
+    };
+    for index in 0..changes.len() {
+        let change = &changes[index];
+        if change.kind != QemuChangeKind::Added
+            || change.apply != QemuChangeApply::Live
+            || !change.subject.starts_with("-device ")
+        {
+            continue;
+        }
+        let Some((_, device)) = new_keyed.iter().find(|(s, _)| *s == change.subject) else {
+            continue;
+        };
+        let orphaned = BACKEND_PROPERTIES.iter().any(|(property, options)| {
+            device.get_property(property).is_some_and(|id| {
+                !options
+                    .iter()
+                    .any(|option| live_backend(changes, &format!("{} {}", option, id)))
+            })
+        });
+        if orphaned {
+            changes[index].apply = QemuChangeApply::Cold;
+            changes[index].commands.clear();
+        }
+    }
+}
+
+/// An added or removed argument with an id, live if QMP can hotplug it.
+fn plug(kind: QemuChangeKind, subject: &str, arg: &QemuArg) -> QemuArgChange {
+    let (before, after, command) = match kind {
+        QemuChangeKind::Removed => (arg.to_args(), Vec::new(), QmpCommand::unplug_from_arg(arg)),
+        _ => (Vec::new(), arg.to_args(), QmpCommand::hotplug_from_arg(arg)),
+    };
+    let mut change = cold(kind, subject, before, after);
+    if let Ok(command) = command {
+        change.apply = QemuChangeApply::Live;
+        change.commands.push(command);
+    }
+    change
+}
+
+/// A changed argument without an id. Shrinking `-m` is live when the new args have a
+/// balloon device, since the guest can be ballooned down to the new size.
+fn modified(option: &str, old: &QemuArg, new: &QemuArg, after: &QemuLaunchArgs) -> QemuArgChange {
+    let mut change = cold(
+        QemuChangeKind::Modified,
+        option,
+        old.to_args(),
+        new.to_args(),
+    );
+    let has_balloon = after.get_args().iter().any(|a| {
+        canonical_option_name(a.key()) == "-device"
+            && a.get_property("driver")
+                .is_some_and(|d| d.starts_with("virtio-balloon"))
+    });
+    if option == "-m"
+        && has_balloon
+        && let (Some((old_size, old_rest)), Some((new_size, new_rest))) =
+            (memory_size(old), memory_size(new))
+        && old_rest == new_rest
+        && new_size <= old_size
+    {
+        change.apply = QemuChangeApply::Live;
+        change
+            .commands
+            .push(QmpCommand::new("balloon").with_arguments(json!({ "value": new_size })));
+    }
+    change
+}
+
+/// The `-m` size in bytes, and the other `-m` properties.
+fn memory_size(arg: &QemuArg) -> Option<(u64, Vec<String>)> {
+    let value = arg.to_args().get(1)?.clone();
+    let mut size = None;
+    let mut rest = Vec::new();
+    for (index, item) in value.split(',').enumerate() {
+        match item.strip_prefix("size=") {
+            Some(s) => size = Some(s.to_string()),
+            None if index == 0 && !item.contains('=') => size = Some(item.to_string()),
+            None => rest.push(item.to_string()),
+        }
+    }
+    Some((parse_size(&size?, 20)?, rest))
+}
@@ -0,0 +1,222 @@
+use serde_json::{Map, Number, Value};
+
+use super::option_table::{canonical_option_name, parse_size, qemu_option_implied_key};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PropertyType {
+    Bool,
+    Int,
+    /// A byte count; keyval accepts suffixes such as `1G`.
+    Size,
+    /// A repeatable string, a list of `{"str": ...}` in QAPI (e.g. `hostfwd`).
+    StrList,
+}
+
+/// Properties whose QAPI type is not a string, per option. Only these are typed when
+/// converting to JSON; QEMU checks JSON arguments strictly, so every other value is
+/// left a string (e.g. `fd` of `-netdev tap` or `port` of an NBD server).
+const TYPED_PROPERTIES: &[(&str, &str, PropertyType)] = {
+    use PropertyType::*;
+    &[
+        ("-blockdev", "read-only", Bool),
+        ("-blockdev", "auto-read-only", Bool),
+        ("-blockdev", "force-share", Bool),
+        ("-blockdev", "cache.direct", Bool),
+        ("-blockdev", "cache.no-flush", Bool),
+        ("-blockdev", "offset", Size),
+        ("-blockdev", "size", Size),
+        ("-blockdev", "cache-size", Size),
+        ("-blockdev", "l2-cache-size", Size),
+        ("-blockdev", "refcount-cache-size", Size),
+        ("-blockdev", "cache-clean-interval", Int),
+        ("-device", "bootindex", Int),
+        ("-device", "chassis", Int),
+        ("-device", "port", Int),
+        ("-device", "slot", Int),
+        ("-device", "lun", Int),
+        ("-device", "scsi-id", Int),
+        ("-device", "channel", Int),
+        ("-device", "vectors", Int),
+        ("-device", "num-queues", Int),
+        ("-device", "queue-size", Int),
+        ("-device", "host_mtu", Int),
+        ("-device", "logical_block_size", Size),
+        ("-device", "physical_block_size", Size),
+        ("-device", "multifunction", Bool),
+        ("-device", "share-rw", Bool),
+        ("-device", "removable", Bool),
+        ("-device", "deflate-on-oom", Bool),
+        ("-netdev", "queues", Int),
+        ("-netdev", "poll-us", Int),
+        ("-netdev", "sndbuf", Size),
+        ("-netdev", "vhost", Bool),
+        ("-netdev", "vhostforce", Bool),
+        ("-netdev", "vnet_hdr", Bool),
+        ("-netdev", "ipv4", Bool),
+        ("-netdev", "ipv6", Bool),
+        ("-netdev", "restrict", Bool),
+        ("-netdev", "hostfwd", StrList),
+        ("-netdev", "guestfwd", StrList),
+        ("-netdev", "dnssearch", StrList),
+        ("-object", "size", Size),
+        ("-object", "align", Size),
+        ("-object", "prealloc", Bool),
+        ("-object", "prealloc-threads", Int),
+        ("-object", "share", Bool),
+        ("-object", "merge", Bool),
+        ("-object", "dump", Bool),
+        ("-object", "reserve", Bool),
+        ("-object", "readonly", Bool),
+        ("-object", "discard-data", Bool),
+        ("-object", "poll-max-ns", Int),
+        ("-object", "poll-grow", Int),
+        ("-object", "poll-shrink", Int),
+    ]
+};
+
+fn property_type(option: &str, key: &str) -> Option<PropertyType> {
+    let option = canonical_option_name(option);
+    TYPED_PROPERTIES
+        .iter()
+        .find(|(o, k, _)| *o == option && *k == key)
+        .map(|(_, _, t)| *t)
+}
+
+/// Converts `key=value,...` items (still `,,`-escaped) into the JSON object QEMU would
+/// accept for the same option. Dotted keys become nested objects and `key.N` lists become
+/// arrays. Values stay strings unless `TYPED_PROPERTIES` knows better. Returns `None`
+/// if a key is given twice (other than a repeatable list such as `hostfwd`), since JSON
+/// cannot hold both values.
+pub(crate) fn opts_to_json(option: &str, items: &[String]) -> Option<Value> {
+    let implied_key = qemu_option_implied_key(option);
+    let mut root = Map::new();
+    for (index, item) in items.iter().enumerate() {
+        let item = item.replace(",,", ",");
+        // A bare key other than the implied one is QemuOpts shorthand for `key=on`.
+        let (key, value, bare) = match item.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string(), false),
+            None => match implied_key {
+                Some(implied) if index == 0 => (implied.to_string(), item, false),
+                _ => (item, "on".to_string(), true),
+            },
+        };
+        let value = match property_type(option, &key) {
+            Some(PropertyType::StrList) => {
+                let entry = serde_json::json!({ "str": value });
+                match root.get_mut(&key) {
+                    Some(Value::Array(list)) => list.push(entry),
+                    Some(_) => return None,
+                    None => {
+                        root.insert(key, Value::Array(vec![entry]));
+                    }
+                }
+                continue;
+            }
+            Some(PropertyType::Bool) => match value.as_str() {
+                "on" | "yes" | "true" => Value::Bool(true),
+                "off" | "no" | "false" => Value::Bool(false),
+                _ => Value::String(value),
+            },
+            Some(PropertyType::Int) => match value.parse::<i64>() {
+                Ok(n) => Value::Number(Number::from(n)),
+                Err(_) => Value::String(value),
+            },
+            Some(PropertyType::Size) => match parse_size(&value, 0) {
+                Some(n) => Value::Number(Number::from(n)),
+                None => Value::String(value),
+            },
+            None if bare => Value::Bool(true),
+            None => Value::String(value),
+        };
+        insert_dotted(&mut root, &key, value)?;
+    }
+    Some(lists_to_arrays(Value::Object(root)))
+}
+
+/// Converts a JSON object into `key=value,...` items (with `,,` escaping applied).
+/// Returns `None` for values that have no keyval equivalent, such as `null`.
+pub(crate) fn json_to_opts(option: &str, value: &Value) -> Option<Vec<String>> {
+    let object = value.as_object()?;
+    let mut items = Vec::new();
+    let implied_key = qemu_option_implied_key(option);
+    if let Some(implied) = implied_key
+        && let Some(Value::String(v)) = object.get(implied)
+    {
+        items.push(v.replace(',', ",,"));
+    }
+    for (key, value) in object {
+        if Some(key.as_str()) == implied_key && value.is_string() {
+            continue;
+        }
+        if property_type(option, key) == Some(PropertyType::StrList)
+            && let Value::Array(list) = value
+        {
+            for entry in list {
+                let s = entry.get("str")?.as_str()?;
+                items.push(format!("{}={}", key, s.replace(',', ",,")));
+            }
+            continue;
+        }
+        flatten(key, value, &mut items)?;
+    }
+    Some(items)
+}
+
+/// Inserts `value` at a dotted key; `None` if the key (or a prefix of it) is taken.
+fn insert_dotted(map: &mut Map<String, Value>, key: &str, value: Value) -> Option<()> {
+    match key.split_once('.') {
+        Some((head, rest)) => {
+            let child = map
+                .entry(head.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            insert_dotted(child.as_object_mut()?, rest, value)
+        }
+        None if map.contains_key(key) => None,
+        None => {
+            map.insert(key.to_string(), value);
+            Some(())
+        }
+    }
+}
+
+fn lists_to_arrays(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let is_list = !map.is_empty()
+                && map
+                    .keys()
+                    .enumerate()
+                    .all(|(i, k)| k.parse::<usize>() == Ok(i));
+            if is_list {
+                Value::Array(map.into_iter().map(|(_, v)| lists_to_arrays(v)).collect())
+            } else {
+                Value::Object(
+                    map.into_iter()
+                        .map(|(k, v)| (k, lists_to_arrays(v)))
+                        .collect(),
+                )
+            }
+        }
+        other => other,
+    }
+}
+
+fn flatten(prefix: &str, value: &Value, items: &mut Vec<String>) -> Option<()> {
+    match value {
+        Value::Null => return None,
+        Value::Bool(b) => items.push(format!("{}={}", prefix, if *b { "on" } else { "off" })),
+        Value::Number(n) => items.push(format!("{}={}", prefix, n)),
+        Value::String(s) => items.push(format!("{}={}", prefix, s.replace(',', ",,"))),
+        Value::Array(list) => {
+            for (i, v) in list.iter().enumerate() {
+                flatten(&format!("{}.{}", prefix, i), v, items)?;
+            }
+        }
+        Value::Object(map) => {
+            for (k, v) in map {
+                flatten(&format!("{}.{}", prefix, k), v, items)?;
+            }
+        }
+    }
+    Some(())
+}
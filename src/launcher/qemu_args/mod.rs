@@ -1,5 +1,18 @@
+mod args_diff;
+mod json_syntax;
+mod option_table;
 mod qemu_arg;
+mod qemu_diagnostic;
 mod qemu_launch_args;
+mod qemu_parse_error;
+mod shell_quote;
+mod validate;
 
+pub use args_diff::{QemuArgChange, QemuArgsDiff, QemuChangeApply, QemuChangeKind};
+pub use option_table::{QemuOptionArity, qemu_option_accepts_json, qemu_option_arity};
+pub(crate) use option_table::{canonical_option_name, parse_size};
 pub use qemu_arg::QemuArg;
-pub use qemu_launch_args::QemuLaunchArgs;
\ No newline at end of file
+pub use qemu_diagnostic::{QemuDiagnostic, QemuDiagnosticKind, QemuSeverity};
+pub use qemu_launch_args::QemuLaunchArgs;
+pub use qemu_parse_error::QemuParseError;
+pub(crate) use shell_quote::{shell_quote, systemd_quote};
@@ -0,0 +1,220 @@
+/// How a QEMU command-line option consumes the token that follows it.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum QemuOptionArity {
+    /// Takes no argument, e.g. `-nographic`.
+    Flag,
+    /// Takes one opaque argument, e.g. `-kernel <path>` or `-append <cmdline>`.
+    Value,
+    /// Takes one `key=value,...` argument parsed by QEMU's option parser, e.g. `-drive`.
+    Opts,
+}
+
+const OPTIONS: &[(&str, QemuOptionArity)] = {
+    use QemuOptionArity::{Flag, Opts, Value};
+    &[
+        // Standard options
+        ("-h", Flag),
+        ("-help", Flag),
+        ("-version", Flag),
+        ("-machine", Opts),
+        ("-cpu", Opts),
+        ("-accel", Opts),
+        ("-smp", Opts),
+        ("-numa", Opts),
+        ("-add-fd", Opts),
+        ("-set", Value),
+        ("-global", Opts),
+        ("-boot", Opts),
+        ("-m", Opts),
+        ("-mem-path", Value),
+        ("-mem-prealloc", Flag),
+        ("-k", Value),
+        ("-audio", Opts),
+        ("-audiodev", Opts),
+        ("-device", Opts),
+        ("-name", Opts),
+        ("-uuid", Value),
+        // Block device options
+        ("-fda", Value),
+        ("-fdb", Value),
+        ("-hda", Value),
+        ("-hdb", Value),
+        ("-hdc", Value),
+        ("-hdd", Value),
+        ("-cdrom", Value),
+        ("-blockdev", Opts),
+        ("-drive", Opts),
+        ("-mtdblock", Value),
+        ("-sd", Value),
+        ("-pflash", Value),
+        ("-snapshot", Flag),
+        ("-fsdev", Opts),
+        ("-virtfs", Opts),
+        ("-iscsi", Opts),
+        // USB options
+        ("-usb", Flag),
+        ("-usbdevice", Value),
+        // Display options
+        ("-display", Opts),
+        ("-nographic", Flag),
+        ("-spice", Opts),
+        ("-portrait", Flag),
+        ("-rotate", Value),
+        ("-vga", Value),
+        ("-full-screen", Flag),
+        ("-g", Value),
+        ("-vnc", Opts),
+        ("-win2k-hack", Flag),
+        ("-no-fd-bootchk", Flag),
+        ("-acpitable", Opts),
+        ("-smbios", Opts),
+        ("-alt-grab", Flag),
+        ("-ctrl-grab", Flag),
+        // Network options
+        ("-nic", Opts),
+        ("-netdev", Opts),
+        ("-net", Opts),
+        // Character device options
+        ("-chardev", Opts),
+        // TPM device options
+        ("-tpmdev", Opts),
+        // Boot image or kernel specific
+        ("-kernel", Value),
+        ("-shim", Value),
+        ("-append", Value),
+        ("-initrd", Value),
+        ("-dtb", Value),
+        ("-fw_cfg", Opts),
+        // Debug/expert options
+        ("-serial", Value),
+        ("-parallel", Value),
+        ("-monitor", Value),
+        ("-qmp", Value),
+        ("-qmp-pretty", Value),
+        ("-mon", Opts),
+        ("-debugcon", Value),
+        ("-pidfile", Value),
+        ("-preconfig", Flag),
+        ("-S", Flag),
+        ("-overcommit", Opts),
+        ("-gdb", Value),
+        ("-s", Flag),
+        ("-d", Value),
+        ("-D", Value),
+        ("-dfilter", Value),
+        ("-seed", Value),
+        ("-L", Value),
+        ("-bios", Value),
+        ("-enable-kvm", Flag),
+        ("-xen-domid", Value),
+        ("-xen-attach", Flag),
+        ("-xen-domid-restrict", Flag),
+        ("-no-reboot", Flag),
+        ("-no-shutdown", Flag),
+        ("-action", Opts),
+        ("-loadvm", Value),
+        ("-daemonize", Flag),
+        ("-option-rom", Value),
+        ("-rtc", Opts),
+        ("-icount", Opts),
+        ("-watchdog-action", Value),
+        ("-echr", Value),
+        ("-incoming", Value),
+        ("-only-migratable", Flag),
+        ("-nodefaults", Flag),
+        ("-sandbox", Opts),
+        ("-readconfig", Value),
+        ("-no-user-config", Flag),
+        ("-trace", Opts),
+        ("-plugin", Opts),
+        ("-async-teardown", Flag),
+        ("-run-with", Opts),
+        ("-runas", Value),
+        ("-chroot", Value),
+        ("-semihosting", Flag),
+        ("-semihosting-config", Opts),
+        ("-enable-fips", Flag),
+        ("-msg", Opts),
+        ("-dump-vmstate", Value),
+        ("-enable-sync-profile", Flag),
+        ("-perfmap", Flag),
+        ("-jitdump", Flag),
+        ("-compat", Opts),
+        ("-object", Opts),
+        ("-writeconfig", Value),
+    ]
+};
+
+/// Options whose argument may be given in JSON syntax instead of `key=value,...`.
+const JSON_OPTIONS: &[&str] = &[
+    "-device",
+    "-object",
+    "-blockdev",
+    "-netdev",
+    "-audiodev",
+    "-display",
+    "-compat",
+];
+
+/// Strips the optional second leading dash (`--drive` is the same as `-drive`) and
+/// resolves the `-M` alias of `-machine`.
+pub(crate) fn canonical_option_name(name: &str) -> &str {
+    let name = match name.strip_prefix("--") {
+        Some(rest) if !rest.is_empty() => &name[1..],
+        _ => name,
+    };
+    match name {
+        "-M" => "-machine",
+        name => name,
+    }
+}
+
+/// Looks up how many arguments a QEMU option takes. Accepts both `-opt` and `--opt`.
+pub fn qemu_option_arity(name: &str) -> Option<QemuOptionArity> {
+    let name = canonical_option_name(name);
+    OPTIONS
+        .iter()
+        .find(|(option, _)| *option == name)
+        .map(|(_, arity)| *arity)
+}
+
+/// Whether QEMU accepts a JSON object as the argument of this option.
+pub fn qemu_option_accepts_json(name: &str) -> bool {
+    JSON_OPTIONS.contains(&canonical_option_name(name))
+}
+
+/// The property an option's leading bare value stands for, e.g. `driver` for
+/// `-device virtio-net-pci,...`. Used when converting between keyval and JSON syntax.
+pub(crate) fn qemu_option_implied_key(name: &str) -> Option<&'static str> {
+    match canonical_option_name(name) {
+        "-device" | "-audiodev" => Some("driver"),
+        "-object" => Some("qom-type"),
+        "-netdev" | "-display" | "-machine" => Some("type"),
+        "-accel" => Some("accel"),
+        "-chardev" => Some("backend"),
+        _ => None,
+    }
+}
+
+/// Parses a QEMU size (`512`, `4G`, `1.5T`); a number without suffix is in units of
+/// `1 << default_shift` bytes.
+pub(crate) fn parse_size(text: &str, default_shift: u32) -> Option<u64> {
+    let text = text.trim();
+    let (number, shift) = match text.char_indices().last()? {
+        (i, c) if c.is_ascii_alphabetic() => {
+            let shift = match c.to_ascii_uppercase() {
+                'B' => 0,
+                'K' => 10,
+                'M' => 20,
+                'G' => 30,
+                'T' => 40,
+                'P' => 50,
+                _ => return None,
+            };
+            (&text[..i], shift)
+        }
+        _ => (text, default_shift),
+    };
+    let number: f64 = number.parse().ok()?;
+    Some((number * (1u64 << shift) as f64) as u64)
+}
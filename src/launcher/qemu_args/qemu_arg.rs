@@ -1,4 +1,11 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::json_syntax::{json_to_opts, opts_to_json};
+use super::option_table::{
+    canonical_option_name, qemu_option_accepts_json, qemu_option_implied_key,
+};
+use super::shell_quote::shell_quote;
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
@@ -6,6 +13,8 @@ pub enum QemuArg {
     Flag(String),
     KeyValue(String, String),
     List(String, Vec<String>),
+    /// JSON-syntax argument (`-device '{"driver":...}'`), rendered as compact JSON.
+    Json(String, Value),
 }
 impl QemuArg {
     pub fn from_flag(flag: impl Into<String>) -> Self {
@@ -20,6 +29,38 @@ impl QemuArg {
         QemuArg::List(key.into(), list.into_iter().map(|s| s.into()).collect())
     }
 
+    pub fn from_json(key: impl Into<String>, value: Value) -> Self {
+        QemuArg::Json(key.into(), value)
+    }
+
+    /// Splits a `key=value,...` argument on the commas QEMU treats as separators.
+    /// Escaped commas (`,,`) stay inside their item, so `to_args` gives back `value` unchanged.
+    /// Yields a `KeyValue` when there is only a single item.
+    pub fn from_opts(key: impl Into<String>, value: &str) -> Self {
+        let mut items = Vec::new();
+        let mut current = String::new();
+        let mut chars = value.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == ',' {
+                if chars.peek() == Some(&',') {
+                    chars.next();
+                    current.push_str(",,");
+                } else {
+                    items.push(std::mem::take(&mut current));
+                }
+            } else {
+                current.push(c);
+            }
+        }
+        items.push(current);
+
+        if items.len() == 1 {
+            QemuArg::KeyValue(key.into(), items.remove(0))
+        } else {
+            QemuArg::List(key.into(), items)
+        }
+    }
+
     pub fn is_flag(&self) -> bool {
         matches!(self, QemuArg::Flag(_))
     }
@@ -32,17 +73,53 @@ impl QemuArg {
         matches!(self, QemuArg::List(_, _))
     }
 
+    pub fn is_json(&self) -> bool {
+        matches!(self, QemuArg::Json(_, _))
+    }
+
     pub fn key(&self) -> &str {
         match self {
             QemuArg::Flag(flag) => flag,
             QemuArg::KeyValue(key, _) => key,
             QemuArg::List(key, _) => key,
+            QemuArg::Json(key, _) => key,
         }
         .as_str()
     }
 
+    /// Looks up a property of a `key=value,...` or JSON argument, with `,,` unescaped.
+    /// A leading bare value (e.g. the driver of `-device`) is returned for its implied key.
+    /// Dotted names (`file.filename`) reach into nested JSON objects.
+    pub fn get_property(&self, name: &str) -> Option<String> {
+        match self {
+            QemuArg::Flag(_) => None,
+            QemuArg::Json(_, value) => match json_property(value, name)? {
+                Value::String(s) => Some(s.clone()),
+                Value::Number(n) => Some(n.to_string()),
+                Value::Bool(b) => Some(if *b { "on" } else { "off" }.to_string()),
+                _ => None,
+            },
+            QemuArg::KeyValue(key, value) if value.trim_start().starts_with('{') => {
+                QemuArg::Json(key.clone(), serde_json::from_str(value).ok()?).get_property(name)
+            }
+            QemuArg::KeyValue(key, value) => find_property(key, std::slice::from_ref(value), name),
+            QemuArg::List(key, list) => find_property(key, list, name),
+        }
+    }
+
+    /// The identifier QEMU uses to refer to this argument: `node-name` for `-blockdev`,
+    /// `id` for everything else.
+    pub fn get_id(&self) -> Option<String> {
+        if self.key_equals("-blockdev") {
+            self.get_property("node-name")
+        } else {
+            self.get_property("id")
+        }
+    }
+
+    /// Compares option names, treating `--opt` and `-opt` as the same option.
     pub fn key_equals(&self, key: &str) -> bool {
-        self.key() == key
+        canonical_option_name(self.key()) == canonical_option_name(key)
     }
 
     pub fn with_key(&self, new_key: impl Into<String>) -> Self {
@@ -51,6 +128,7 @@ impl QemuArg {
             QemuArg::Flag(_) => QemuArg::Flag(new_key),
             QemuArg::KeyValue(_, value) => QemuArg::KeyValue(new_key, value.clone()),
             QemuArg::List(_, list) => QemuArg::List(new_key, list.clone()),
+            QemuArg::Json(_, value) => QemuArg::Json(new_key, value.clone()),
         }
     }
 
@@ -62,14 +140,79 @@ impl QemuArg {
                 let joined = list.join(",");
                 vec![key.clone(), joined]
             }
+            QemuArg::Json(key, value) => vec![key.clone(), value.to_string()],
         }
     }
 
+    /// Converts this argument to JSON syntax. Returns `None` if the option does not
+    /// accept JSON (see `qemu_option_accepts_json`), the argument is a flag, or a key
+    /// is repeated in a way JSON cannot express.
+    pub fn to_json_syntax(&self) -> Option<QemuArg> {
+        if !qemu_option_accepts_json(self.key()) {
+            return None;
+        }
+        match self {
+            QemuArg::Flag(_) => None,
+            QemuArg::Json(_, _) => Some(self.clone()),
+            QemuArg::KeyValue(key, value) if value.trim_start().starts_with('{') => {
+                let value = serde_json::from_str(value).ok()?;
+                Some(QemuArg::Json(key.clone(), value))
+            }
+            QemuArg::KeyValue(key, value) => Some(QemuArg::Json(
+                key.clone(),
+                opts_to_json(key, std::slice::from_ref(value))?,
+            )),
+            QemuArg::List(key, list) => Some(QemuArg::Json(key.clone(), opts_to_json(key, list)?)),
+        }
+    }
+
+    /// Converts a JSON-syntax argument to `key=value,...` syntax. Arguments that are
+    /// already in keyval form are returned unchanged; `None` means the JSON value has
+    /// no keyval equivalent.
+    pub fn to_opts_syntax(&self) -> Option<QemuArg> {
+        let (key, value) = match self {
+            QemuArg::Json(key, value) => (key, value.clone()),
+            QemuArg::KeyValue(key, value) if value.trim_start().starts_with('{') => {
+                (key, serde_json::from_str(value).ok()?)
+            }
+            _ => return Some(self.clone()),
+        };
+        let mut items = json_to_opts(key, &value)?;
+        if items.len() == 1 {
+            Some(QemuArg::KeyValue(key.clone(), items.remove(0)))
+        } else {
+            Some(QemuArg::List(key.clone(), items))
+        }
+    }
+
+    /// The tokens quoted for a POSIX shell; splitting the result like a shell gives back
+    /// `to_args()` exactly.
     pub fn to_command_line(&self) -> String {
         self.to_args()
             .iter()
-            .map(|s| format!("\"{}\"", s.replace('"', "\\\"")))
+            .map(|s| shell_quote(s, false))
             .collect::<Vec<_>>()
             .join(" ")
     }
 }
+
+fn find_property(option: &str, items: &[String], name: &str) -> Option<String> {
+    items
+        .iter()
+        .enumerate()
+        .find_map(|(index, item)| match item.split_once('=') {
+            Some((key, value)) if key == name => Some(value.replace(",,", ",")),
+            None if index == 0 && qemu_option_implied_key(option) == Some(name) => {
+                Some(item.replace(",,", ","))
+            }
+            _ => None,
+        })
+}
+
+fn json_property<'a>(value: &'a Value, name: &str) -> Option<&'a Value> {
+    if let Some(found) = value.get(name) {
+        return Some(found);
+    }
+    let (head, rest) = name.split_once('.')?;
+    json_property(value.get(head)?, rest)
+}
@@ -0,0 +1,42 @@
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QemuSeverity {
+    /// Probably a mistake, but QEMU starts.
+    Warning,
+    /// QEMU refuses to start or the VM will not work as configured.
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum QemuDiagnosticKind {
+    /// An option that takes effect once was given several times.
+    DuplicateOption,
+    /// Two arguments of the same namespace (devices, block nodes, ...) share an id.
+    DuplicateId,
+    /// A `netdev=`, `drive=`, `chardev=`, `audiodev=` or `memdev=` id that is not defined.
+    UndefinedReference,
+    /// Options that cannot be combined, such as `-nographic` and `-display`.
+    ConflictingOptions,
+    /// A listening socket path that is also used by another socket or VM.
+    SharedSocket,
+    MissingFile,
+}
+
+/// One finding of `QemuLaunchArgs::validate`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QemuDiagnostic {
+    pub kind: QemuDiagnosticKind,
+    pub severity: QemuSeverity,
+    /// The option, id or path the finding is about.
+    pub subject: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for QemuDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            QemuSeverity::Warning => "warning",
+            QemuSeverity::Error => "error",
+        };
+        write!(f, "[{}] {}: {}", severity, self.subject, self.message)
+    }
+}
@@ -1,7 +1,12 @@
 use serde::{Deserialize, Serialize};
 
-use super::QemuArg;
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Default,Serialize,Deserialize)]
+use super::args_diff::diff;
+use super::option_table::{canonical_option_name, qemu_option_accepts_json, qemu_option_arity};
+use super::shell_quote::{shell_quote, systemd_quote};
+use super::validate::validate;
+use super::{QemuArg, QemuArgsDiff, QemuDiagnostic, QemuOptionArity, QemuParseError};
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct QemuLaunchArgs {
     #[serde(rename = "qemuBinary")]
     binary: String,
@@ -40,6 +45,11 @@ impl QemuLaunchArgs {
         self
     }
 
+    pub fn with_json(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.args.push(QemuArg::from_json(key, value));
+        self
+    }
+
     pub fn with_args(mut self, args: impl IntoIterator<Item = QemuArg>) -> Self {
         self.args.extend(args);
         self
@@ -55,41 +65,111 @@ impl QemuLaunchArgs {
         self
     }
 
-    pub fn parse_command_line(command_line: &str) -> Result<Self, String> {
-        let tokens =
-            shell_words::split(command_line).map_err(|e| format!("Tokenization failed: {}", e))?;
+    /// Adds `-pidfile <path>`, replacing any earlier one.
+    pub fn with_pidfile(mut self, path: impl Into<String>) -> Self {
+        self.replace_arg(QemuArg::from_key_value("-pidfile", path));
+        self
+    }
 
-        if tokens.is_empty() {
-            return Err("Empty command line".to_string());
+    /// Adds `-daemonize`. `QemuProcess::launch` then requires a `-pidfile` and adopts
+    /// the daemon once the spawned process has exited.
+    pub fn with_daemonize(mut self) -> Self {
+        if !self.is_daemonized() {
+            self.args.push(QemuArg::from_flag("-daemonize"));
         }
+        self
+    }
+
+    pub fn get_pidfile(&self) -> Option<&str> {
+        self.args.iter().rev().find_map(|a| match a {
+            QemuArg::KeyValue(key, path) if canonical_option_name(key) == "-pidfile" => {
+                Some(path.as_str())
+            }
+            _ => None,
+        })
+    }
+
+    pub fn is_daemonized(&self) -> bool {
+        self.args
+            .iter()
+            .any(|a| canonical_option_name(a.key()) == "-daemonize")
+    }
+
+    /// Splits `command_line` like a POSIX shell and parses the resulting argv.
+    pub fn parse_command_line(command_line: &str) -> Result<Self, QemuParseError> {
+        Self::parse_args(shell_words::split(command_line)?)
+    }
 
-        let mut iter = tokens.into_iter();
-        let binary = iter.next().unwrap();
+    /// Parses an argv (binary first) using QEMU's option table. Option tokens are kept
+    /// verbatim, so `parse_args(argv)?.to_args() == argv` holds whenever the positionals
+    /// come after the options (the order `to_args` emits them in).
+    pub fn parse_args<I, S>(argv: I) -> Result<Self, QemuParseError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::parse(argv, false)
+    }
+
+    /// `parse_args` for a command line that QEMU already accepted (an attached
+    /// process): options missing from the table are kept as a flag if the next token
+    /// starts with `-` (or there is none), otherwise as an option taking that token.
+    pub(crate) fn parse_args_lenient<I, S>(argv: I) -> Result<Self, QemuParseError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::parse(argv, true)
+    }
+
+    fn parse<I, S>(argv: I, lenient: bool) -> Result<Self, QemuParseError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut iter = argv.into_iter().map(Into::into).peekable();
+        let binary = iter.next().ok_or(QemuParseError::EmptyCommandLine)?;
 
         let mut args = Vec::new();
         let mut positionals = Vec::new();
 
         while let Some(token) = iter.next() {
-            if token.starts_with('-') {
-                if let Some(next) = iter.clone().next() {
-                    if next.starts_with('-') {
-                        args.push(QemuArg::from_flag(token));
-                    } else if next.contains('=') || next.contains(',') {
-                        iter.next();
-                        let list = next
-                            .split(',')
-                            .map(|s| s.trim().to_string())
-                            .collect::<Vec<_>>();
-                        args.push(QemuArg::from_list(token, list));
-                    } else {
-                        iter.next();
-                        args.push(QemuArg::from_key_value(token, next));
-                    }
-                } else {
-                    args.push(QemuArg::from_flag(token));
+            if !token.starts_with('-') || token == "-" {
+                positionals.push(token);
+                continue;
+            }
+
+            let arity = match qemu_option_arity(&token) {
+                Some(arity) => arity,
+                None if !lenient => return Err(QemuParseError::UnknownOption(token)),
+                None => match iter.peek() {
+                    Some(next) if !next.starts_with('-') => QemuOptionArity::Value,
+                    _ => QemuOptionArity::Flag,
+                },
+            };
+            if arity == QemuOptionArity::Flag {
+                args.push(QemuArg::from_flag(token));
+                continue;
+            }
+
+            let value = iter
+                .next()
+                .ok_or_else(|| QemuParseError::MissingValue(token.clone()))?;
+            if arity == QemuOptionArity::Opts
+                && qemu_option_accepts_json(&token)
+                && value.trim_start().starts_with('{')
+            {
+                if let Err(error) = serde_json::from_str::<serde_json::Value>(&value) {
+                    return Err(QemuParseError::InvalidJson {
+                        option: token,
+                        error,
+                    });
                 }
+                args.push(QemuArg::from_key_value(token, value));
+            } else if arity == QemuOptionArity::Opts {
+                args.push(QemuArg::from_opts(token, &value));
             } else {
-                positionals.push(token);
+                args.push(QemuArg::from_key_value(token, value));
             }
         }
 
@@ -133,26 +213,68 @@ impl QemuLaunchArgs {
         result
     }
 
+    /// The command line quoted for a POSIX shell, so that pasting it into a shell runs
+    /// exactly `to_args()`, and `parse_command_line` reads back the same tokens.
     pub fn to_command_line(&self) -> String {
-        fn shell_escape(s: &str) -> String {
-            if s.contains(' ') || s.contains('"') || s.contains(',') {
-                format!("\"{}\"", s.replace('"', "\\\""))
-            } else {
-                s.to_string()
-            }
-        }
+        self.to_args()
+            .iter()
+            .enumerate()
+            .map(|(i, s)| shell_quote(s, i == 0))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
 
+    /// The command line in the syntax of a systemd `ExecStart=` (or `ExecStartPre=`,
+    /// ...) setting, with `$` and `%` escaped so systemd passes `to_args()` unchanged.
+    pub fn to_systemd_command_line(&self) -> String {
         self.to_args()
             .iter()
-            .map(|s| shell_escape(s))
+            .map(|s| systemd_quote(s))
             .collect::<Vec<_>>()
             .join(" ")
     }
 
+    /// Lints the args for common mistakes: repeated single-use options, duplicate or
+    /// undefined ids, conflicting options, shared listening sockets and missing files.
+    pub fn validate(&self) -> Vec<QemuDiagnostic> {
+        validate(self, [])
+    }
+
+    /// Like `validate`, and also reports listening sockets that `others` (the args of
+    /// other VMs) use as well.
+    pub fn validate_with<'a>(
+        &self,
+        others: impl IntoIterator<Item = &'a QemuLaunchArgs>,
+    ) -> Vec<QemuDiagnostic> {
+        validate(self, others)
+    }
+
+    /// Compares these args with `new`. Arguments with an id are matched by option and
+    /// id rather than by position, and each change is marked as live (applicable
+    /// through QMP) or as needing a restart.
+    pub fn diff(&self, new: &QemuLaunchArgs) -> QemuArgsDiff {
+        diff(self, new)
+    }
+
     pub fn get_arg(&self, key: &str) -> Option<&QemuArg> {
         self.args.iter().find(|a| a.key_equals(key))
     }
 
+    /// Finds the argument of option `key` whose id (see `QemuArg::get_id`) is `id`.
+    pub fn get_arg_by_id(&self, key: &str, id: &str) -> Option<&QemuArg> {
+        self.args
+            .iter()
+            .find(|a| a.key_equals(key) && a.get_id().as_deref() == Some(id))
+    }
+
+    pub fn remove_arg_by_id(&mut self, key: &str, id: &str) -> Option<QemuArg> {
+        let index = self
+            .args
+            .iter()
+            .position(|a| a.key_equals(key) && a.get_id().as_deref() == Some(id))?;
+        Some(self.args.remove(index))
+    }
+
     pub fn remove_arg(&mut self, key: &str) {
         self.args.retain(|a| !a.key_equals(key));
     }
@@ -162,6 +284,25 @@ impl QemuLaunchArgs {
         self.args.push(new_arg);
     }
 
+    /// Replaces the argument with the same key and id (see `QemuArg::get_id`) in place,
+    /// leaving other arguments of that key alone. Appends `new_arg` if there is no such
+    /// argument (or it has no id) and returns the replaced one.
+    pub fn replace_arg_by_id(&mut self, new_arg: QemuArg) -> Option<QemuArg> {
+        let id = new_arg.get_id();
+        let index = id.as_deref().and_then(|id| {
+            self.args
+                .iter()
+                .position(|a| a.key_equals(new_arg.key()) && a.get_id().as_deref() == Some(id))
+        });
+        match index {
+            Some(index) => Some(std::mem::replace(&mut self.args[index], new_arg)),
+            None => {
+                self.args.push(new_arg);
+                None
+            }
+        }
+    }
+
     pub fn clear_positionals(&mut self) {
         self.positionals.clear();
     }
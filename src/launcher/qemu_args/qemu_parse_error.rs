@@ -0,0 +1,43 @@
+#[derive(Debug)]
+pub enum QemuParseError {
+    Tokenize(shell_words::ParseError),
+    EmptyCommandLine,
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidJson {
+        option: String,
+        error: serde_json::Error,
+    },
+}
+
+impl std::fmt::Display for QemuParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QemuParseError::Tokenize(e) => write!(f, "Tokenization failed: {}", e),
+            QemuParseError::EmptyCommandLine => write!(f, "Empty command line"),
+            QemuParseError::UnknownOption(option) => write!(f, "Unknown option: {}", option),
+            QemuParseError::MissingValue(option) => {
+                write!(f, "Option {} requires an argument", option)
+            }
+            QemuParseError::InvalidJson { option, error } => {
+                write!(f, "Invalid JSON argument for {}: {}", option, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for QemuParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QemuParseError::Tokenize(e) => Some(e),
+            QemuParseError::InvalidJson { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<shell_words::ParseError> for QemuParseError {
+    fn from(e: shell_words::ParseError) -> Self {
+        QemuParseError::Tokenize(e)
+    }
+}
@@ -0,0 +1,42 @@
+/// Characters that never need quoting, in a POSIX shell or in a systemd command line.
+fn is_safe(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+        || matches!(c, '_' | '-' | '+' | '@' | '%' | ':' | ',' | '.' | '/' | '=')
+}
+
+/// Quotes `word` for a POSIX shell: safe words are left alone, anything else is put in
+/// single quotes (with `'` written as `'\''`). `command_word` also quotes a `=`, which
+/// would otherwise turn the first word into a variable assignment.
+pub(crate) fn shell_quote(word: &str, command_word: bool) -> String {
+    let safe =
+        !word.is_empty() && word.chars().all(is_safe) && !(command_word && word.contains('='));
+    if safe {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// Quotes `word` for a systemd `ExecStart=` line. systemd expands `$VAR` and `%`
+/// specifiers even inside quotes, so both are doubled; quoted words use C escapes.
+pub(crate) fn systemd_quote(word: &str) -> String {
+    if !word.is_empty() && word.chars().all(|c| is_safe(c) && c != '%') {
+        return word.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in word.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '$' => quoted.push_str("$$"),
+            '%' => quoted.push_str("%%"),
+            c if c.is_ascii_control() => quoted.push_str(&format!("\\x{:02x}", c as u32)),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,173 @@
+use std::path::Path;
+
+use super::option_table::canonical_option_name;
+use super::{QemuArg, QemuDiagnostic, QemuDiagnosticKind, QemuLaunchArgs, QemuSeverity};
+use crate::launcher::preflight::unix_sockets;
+
+use QemuDiagnosticKind::*;
+use QemuSeverity::{Error, Warning};
+
+/// Options of which QEMU only honours the last occurrence (or merges them silently).
+const SINGLETONS: &[&str] = &[
+    "-m", "-smp", "-cpu", "-name", "-uuid", "-kernel", "-initrd", "-append", "-dtb", "-bios",
+    "-pidfile",
+];
+
+/// Properties that refer to the id of another argument, and the namespace of that id.
+const REFERENCES: &[(&str, &str)] = &[
+    ("netdev", "netdev"),
+    ("drive", "block"),
+    ("chardev", "chardev"),
+    ("audiodev", "audiodev"),
+    ("memdev", "object"),
+    ("memory-backend", "object"),
+];
+
+/// Options whose single value is a local file QEMU opens at startup.
+const FILE_OPTIONS: &[&str] = &[
+    "-hda", "-hdb", "-hdc", "-hdd", "-cdrom", "-kernel", "-initrd",
+];
+
+pub(crate) fn validate<'a>(
+    args: &QemuLaunchArgs,
+    others: impl IntoIterator<Item = &'a QemuLaunchArgs>,
+) -> Vec<QemuDiagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut push = |kind, severity, subject: &str, message: String| {
+        diagnostics.push(QemuDiagnostic {
+            kind,
+            severity,
+            subject: subject.to_string(),
+            message,
+        })
+    };
+
+    for option in SINGLETONS {
+        let count = args
+            .get_args()
+            .iter()
+            .filter(|a| canonical_option_name(a.key()) == *option)
+            .count();
+        if count > 1 {
+            push(
+                DuplicateOption,
+                Warning,
+                option,
+                format!("given {} times; later ones override earlier ones", count),
+            );
+        }
+    }
+
+    // (namespace, id)
+    let mut ids: Vec<(&str, String)> = Vec::new();
+    for arg in args.get_args() {
+        let Some(id) = arg.get_id() else {
+            continue;
+        };
+        let namespace = namespace(arg);
+        if ids.iter().any(|(n, i)| *n == namespace && *i == id) {
+            push(
+                DuplicateId,
+                Error,
+                &id,
+                format!(
+                    "defined more than once by {}",
+                    canonical_option_name(arg.key())
+                ),
+            );
+        } else {
+            ids.push((namespace, id));
+        }
+    }
+
+    for arg in args.get_args() {
+        if matches!(canonical_option_name(arg.key()), "-drive" | "-blockdev") {
+            continue;
+        }
+        for (property, namespace) in REFERENCES {
+            if let Some(id) = arg.get_property(property)
+                && !ids.iter().any(|(n, i)| n == namespace && *i == id)
+            {
+                push(
+                    UndefinedReference,
+                    Error,
+                    &id,
+                    format!(
+                        "{} {}={} is not defined",
+                        canonical_option_name(arg.key()),
+                        property,
+                        id
+                    ),
+                );
+            }
+        }
+    }
+
+    let has = |option: &str| {
+        args.get_args()
+            .iter()
+            .any(|a| canonical_option_name(a.key()) == option)
+    };
+    if has("-nographic") && has("-display") {
+        push(
+            ConflictingOptions,
+            Error,
+            "-nographic",
+            "cannot be combined with -display".to_string(),
+        );
+    }
+
+    let sockets = unix_sockets(args);
+    let other_sockets: Vec<(String, bool)> = others.into_iter().flat_map(unix_sockets).collect();
+    for (index, (path, server)) in sockets.iter().enumerate() {
+        if !server || path.is_empty() {
+            continue;
+        }
+        if sockets[..index].iter().any(|(p, s)| *s && p == path) {
+            push(
+                SharedSocket,
+                Error,
+                path,
+                "used by more than one listening socket".to_string(),
+            );
+        } else if other_sockets.iter().any(|(p, _)| p == path) {
+            push(
+                SharedSocket,
+                Error,
+                path,
+                "also used by another VM".to_string(),
+            );
+        }
+    }
+
+    let files = args
+        .get_args()
+        .iter()
+        .filter(|a| FILE_OPTIONS.contains(&canonical_option_name(a.key())))
+        .filter_map(|a| a.to_args().get(1).cloned())
+        .chain(args.get_positionals().iter().cloned());
+    for file in files {
+        if is_local_path(&file) && !Path::new(&file).exists() {
+            push(MissingFile, Error, &file, "does not exist".to_string());
+        }
+    }
+
+    diagnostics
+}
+
+/// QEMU keeps a separate id space per option, except that `-drive` ids and
+/// `-blockdev` node names are both block node names.
+fn namespace(arg: &QemuArg) -> &str {
+    match canonical_option_name(arg.key()) {
+        "-drive" | "-blockdev" => "block",
+        name => name.trim_start_matches('-'),
+    }
+}
+
+/// Skips protocol file names such as `nbd:...` or `https://...`.
+fn is_local_path(path: &str) -> bool {
+    match path.split_once(':') {
+        Some((prefix, _)) => prefix.contains('/'),
+        None => !path.is_empty(),
+    }
+}
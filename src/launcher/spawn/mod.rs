@@ -0,0 +1,9 @@
+mod passed_fd;
+mod pre_exec;
+mod process_group;
+mod rlimit;
+
+pub use passed_fd::QemuPassedFd;
+pub(crate) use pre_exec::configure_command;
+pub use process_group::QemuProcessGroup;
+pub use rlimit::{QemuRlimit, QemuRlimitResource};
@@ -0,0 +1,96 @@
+use std::hash::{Hash, Hasher};
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::sync::Arc;
+
+use crate::launcher::QemuArg;
+use crate::launcher::options::escape_option_value;
+
+/// An open file descriptor handed to QEMU at a fixed number, e.g. a pre-opened
+/// socket or tap device.
+///
+/// With `fdset`, an `-add-fd fd=<n>,set=<set>` argument is added so QEMU can open it
+/// as `/dev/fdset/<set>`; otherwise refer to the number directly (`-netdev tap,fd=<n>`).
+#[derive(Debug, Clone)]
+pub struct QemuPassedFd {
+    fd: Arc<OwnedFd>,
+    child_fd: i32,
+    fdset: Option<u32>,
+    opaque: Option<String>,
+}
+
+impl QemuPassedFd {
+    /// `child_fd` is the descriptor number inside QEMU; it must be 3 or above.
+    pub fn new(fd: impl Into<OwnedFd>, child_fd: i32) -> Self {
+        Self {
+            fd: Arc::new(fd.into()),
+            child_fd,
+            fdset: None,
+            opaque: None,
+        }
+    }
+
+    pub fn fdset(fd: impl Into<OwnedFd>, child_fd: i32, set: u32) -> Self {
+        Self::new(fd, child_fd).with_fdset(set)
+    }
+
+    pub fn with_fdset(mut self, set: u32) -> Self {
+        self.fdset = Some(set);
+        self
+    }
+
+    /// Free-form text QEMU reports for the descriptor in `query-fdsets`.
+    pub fn with_opaque(mut self, opaque: impl Into<String>) -> Self {
+        self.opaque = Some(opaque.into());
+        self
+    }
+
+    pub fn get_fd(&self) -> &OwnedFd {
+        &self.fd
+    }
+
+    pub(crate) fn get_shared_fd(&self) -> Arc<OwnedFd> {
+        self.fd.clone()
+    }
+
+    pub fn get_child_fd(&self) -> i32 {
+        self.child_fd
+    }
+
+    pub fn get_fdset(&self) -> Option<u32> {
+        self.fdset
+    }
+
+    pub fn get_opaque(&self) -> Option<&str> {
+        self.opaque.as_deref()
+    }
+
+    /// The `-add-fd` argument for this descriptor, if it belongs to an fd set.
+    pub fn to_add_fd_arg(&self) -> Option<QemuArg> {
+        let set = self.fdset?;
+        let mut items = vec![format!("fd={}", self.child_fd), format!("set={}", set)];
+        if let Some(opaque) = &self.opaque {
+            items.push(format!("opaque={}", escape_option_value(opaque)));
+        }
+        Some(QemuArg::from_list("-add-fd", items))
+    }
+}
+
+impl PartialEq for QemuPassedFd {
+    fn eq(&self, other: &Self) -> bool {
+        self.fd.as_raw_fd() == other.fd.as_raw_fd()
+            && self.child_fd == other.child_fd
+            && self.fdset == other.fdset
+            && self.opaque == other.opaque
+    }
+}
+
+impl Eq for QemuPassedFd {}
+
+impl Hash for QemuPassedFd {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.fd.as_raw_fd().hash(state);
+        self.child_fd.hash(state);
+        self.fdset.hash(state);
+        self.opaque.hash(state);
+    }
+}
@@ -0,0 +1,180 @@
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::sync::Arc;
+
+use tokio::process::Command;
+
+use super::QemuProcessGroup;
+use crate::launcher::QemuLaunchOptions;
+
+/// Everything that has to happen in the forked child before exec. Built in the
+/// parent so the child only makes async-signal-safe calls and never allocates.
+struct ChildSetup {
+    /// `cgroup.procs` of the cgroup to join, opened close-on-exec.
+    cgroup_procs: Option<OwnedFd>,
+    new_session: bool,
+    rlimits: Vec<(i32, libc::rlimit)>,
+    umask: Option<libc::mode_t>,
+    fds: Vec<(Arc<OwnedFd>, i32)>,
+    /// Scratch space for the temporary duplicates of `fds`, allocated up front.
+    temp_fds: Vec<i32>,
+    max_child_fd: i32,
+    gid: Option<libc::gid_t>,
+    uid: Option<libc::uid_t>,
+    parent_death_signal: Option<i32>,
+    parent_pid: libc::pid_t,
+}
+
+/// Applies the environment, working directory, process group and child setup of
+/// `options` to `cmd`. With `cgroup_procs`, the child joins that cgroup first.
+pub(crate) fn configure_command(
+    cmd: &mut Command,
+    options: &QemuLaunchOptions,
+    cgroup_procs: Option<OwnedFd>,
+) -> std::io::Result<()> {
+    if options.get_env_clear() {
+        let kept: Vec<_> = options
+            .get_env_inherit()
+            .iter()
+            .filter_map(|key| std::env::var_os(key).map(|value| (key.clone(), value)))
+            .collect();
+        cmd.env_clear();
+        cmd.envs(kept);
+    }
+    for key in options.get_env_remove() {
+        cmd.env_remove(key);
+    }
+    cmd.envs(options.get_envs().iter().map(|(k, v)| (k, v)));
+    if let Some(dir) = options.get_current_dir() {
+        cmd.current_dir(dir);
+    }
+    match options.get_process_group() {
+        QemuProcessGroup::Inherit | QemuProcessGroup::NewSession => {}
+        QemuProcessGroup::NewGroup => {
+            cmd.process_group(0);
+        }
+        QemuProcessGroup::Join(pgid) => {
+            cmd.process_group(pgid);
+        }
+    }
+
+    let mut child_fds: Vec<i32> = Vec::new();
+    for passed in options.get_passed_fds() {
+        let child_fd = passed.get_child_fd();
+        if child_fd < 3 || child_fds.contains(&child_fd) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid or duplicate child fd {} for a passed fd", child_fd),
+            ));
+        }
+        child_fds.push(child_fd);
+    }
+
+    let setup = ChildSetup {
+        cgroup_procs,
+        new_session: options.get_process_group() == QemuProcessGroup::NewSession,
+        rlimits: options
+            .get_rlimits()
+            .iter()
+            .map(|l| (l.get_resource().to_libc(), l.to_libc()))
+            .collect(),
+        umask: options.get_umask().map(|m| m as libc::mode_t),
+        fds: options
+            .get_passed_fds()
+            .iter()
+            .map(|p| (p.get_shared_fd(), p.get_child_fd()))
+            .collect(),
+        temp_fds: vec![-1; child_fds.len()],
+        max_child_fd: child_fds.iter().copied().max().unwrap_or(2),
+        gid: options.get_gid(),
+        uid: options.get_uid(),
+        parent_death_signal: options.get_parent_death_signal(),
+        // SAFETY: getpid(2) has no preconditions.
+        parent_pid: unsafe { libc::getpid() },
+    };
+    if setup.is_empty() {
+        return Ok(());
+    }
+    // SAFETY: `ChildSetup::run` only makes async-signal-safe libc calls and does not
+    // allocate, as required between fork and exec.
+    unsafe {
+        let mut setup = setup;
+        cmd.pre_exec(move || setup.run());
+    }
+    Ok(())
+}
+
+fn check(ret: libc::c_int) -> std::io::Result<()> {
+    if ret == -1 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+impl ChildSetup {
+    fn is_empty(&self) -> bool {
+        self.cgroup_procs.is_none()
+            && !self.new_session
+            && self.rlimits.is_empty()
+            && self.umask.is_none()
+            && self.fds.is_empty()
+            && self.gid.is_none()
+            && self.uid.is_none()
+            && self.parent_death_signal.is_none()
+    }
+
+    /// Runs in the child. The cgroup is joined first so everything the child does is
+    /// accounted to it. Limits are set before dropping privileges so hard limits can
+    /// still be raised; the parent-death signal is set last since credential changes
+    /// clear it.
+    fn run(&mut self) -> std::io::Result<()> {
+        // SAFETY (whole block): plain syscalls on values prepared by the parent.
+        unsafe {
+            if let Some(procs) = &self.cgroup_procs
+                && libc::write(procs.as_raw_fd(), b"0".as_ptr().cast(), 1) != 1
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+            if self.new_session {
+                check(libc::setsid())?;
+            }
+            for (resource, limit) in &self.rlimits {
+                check(libc::setrlimit(*resource as _, limit))?;
+            }
+            if let Some(mask) = self.umask {
+                libc::umask(mask);
+            }
+
+            // Move every source above the highest target first, so a source that
+            // happens to sit on another entry's target number is not clobbered.
+            for ((fd, _), temp) in self.fds.iter().zip(self.temp_fds.iter_mut()) {
+                *temp = libc::fcntl(fd.as_raw_fd(), libc::F_DUPFD_CLOEXEC, self.max_child_fd + 1);
+                check(*temp)?;
+            }
+            for ((_, child_fd), temp) in self.fds.iter().zip(&self.temp_fds) {
+                // dup2 clears FD_CLOEXEC on the target, so it survives exec.
+                check(libc::dup2(*temp, *child_fd))?;
+            }
+
+            // Drop supplementary groups when switching away from root.
+            if (self.gid.is_some() || self.uid.is_some()) && libc::getuid() == 0 {
+                check(libc::setgroups(0, std::ptr::null()))?;
+            }
+            if let Some(gid) = self.gid {
+                check(libc::setgid(gid))?;
+            }
+            if let Some(uid) = self.uid {
+                check(libc::setuid(uid))?;
+            }
+
+            if let Some(signal) = self.parent_death_signal {
+                check(libc::prctl(libc::PR_SET_PDEATHSIG, signal as libc::c_ulong))?;
+                // The parent may have died before prctl took effect.
+                if libc::getppid() != self.parent_pid {
+                    return Err(std::io::Error::from_raw_os_error(libc::ESRCH));
+                }
+            }
+        }
+        Ok(())
+    }
+}
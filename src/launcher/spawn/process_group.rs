@@ -0,0 +1,13 @@
+/// Session / process group the QEMU process is placed in.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Default)]
+pub enum QemuProcessGroup {
+    /// Stay in the orchestrator's process group (terminal signals reach QEMU too).
+    #[default]
+    Inherit,
+    /// A new process group led by QEMU.
+    NewGroup,
+    /// Join an existing process group.
+    Join(i32),
+    /// A new session (`setsid`), which also detaches QEMU from the controlling terminal.
+    NewSession,
+}
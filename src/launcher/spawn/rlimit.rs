@@ -0,0 +1,71 @@
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum QemuRlimitResource {
+    AddressSpace,
+    Core,
+    Cpu,
+    Data,
+    FileSize,
+    MemLock,
+    NoFile,
+    NProc,
+    Stack,
+}
+
+impl QemuRlimitResource {
+    pub(crate) fn to_libc(self) -> i32 {
+        (match self {
+            QemuRlimitResource::AddressSpace => libc::RLIMIT_AS,
+            QemuRlimitResource::Core => libc::RLIMIT_CORE,
+            QemuRlimitResource::Cpu => libc::RLIMIT_CPU,
+            QemuRlimitResource::Data => libc::RLIMIT_DATA,
+            QemuRlimitResource::FileSize => libc::RLIMIT_FSIZE,
+            QemuRlimitResource::MemLock => libc::RLIMIT_MEMLOCK,
+            QemuRlimitResource::NoFile => libc::RLIMIT_NOFILE,
+            QemuRlimitResource::NProc => libc::RLIMIT_NPROC,
+            QemuRlimitResource::Stack => libc::RLIMIT_STACK,
+        }) as i32
+    }
+}
+
+/// A resource limit set in the QEMU process before exec. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct QemuRlimit {
+    resource: QemuRlimitResource,
+    soft: Option<u64>,
+    hard: Option<u64>,
+}
+
+impl QemuRlimit {
+    pub fn new(resource: QemuRlimitResource, soft: Option<u64>, hard: Option<u64>) -> Self {
+        Self {
+            resource,
+            soft,
+            hard,
+        }
+    }
+
+    /// Same soft and hard limit.
+    pub fn fixed(resource: QemuRlimitResource, limit: u64) -> Self {
+        Self::new(resource, Some(limit), Some(limit))
+    }
+
+    pub fn get_resource(&self) -> QemuRlimitResource {
+        self.resource
+    }
+
+    pub fn get_soft(&self) -> Option<u64> {
+        self.soft
+    }
+
+    pub fn get_hard(&self) -> Option<u64> {
+        self.hard
+    }
+
+    pub(crate) fn to_libc(self) -> libc::rlimit {
+        let value = |v: Option<u64>| v.map_or(libc::RLIM_INFINITY, |v| v as libc::rlim_t);
+        libc::rlimit {
+            rlim_cur: value(self.soft),
+            rlim_max: value(self.hard),
+        }
+    }
+}
@@ -15,6 +15,7 @@ macro_rules! impl_qmp_command_constructors {
 }
 
 impl_qmp_command_constructors! {
+    qmp_capabilities => "qmp_capabilities",
     quit => "quit",
     system_powerdown => "system_powerdown",
     stop => "stop",
@@ -29,6 +30,8 @@ impl_qmp_command_constructors! {
     blockdev_del => "blockdev-del",
     device_add => "device_add",
     device_del => "device_del",
+    netdev_add => "netdev_add",
+    netdev_del => "netdev_del",
     query_status => "query-status",
     query_version => "query-version",
     query_commands => "query-commands",
@@ -0,0 +1,67 @@
+use serde_json::{Value, json};
+
+use super::{QmpCommand, QmpHotplugError};
+use crate::launcher::QemuArg;
+
+/// (option, add command, delete command, id property)
+type HotplugEntry = (&'static str, &'static str, &'static str, &'static str);
+
+/// Launch options that have a QMP hotplug counterpart.
+const HOTPLUG_OPTIONS: &[HotplugEntry] = &[
+    ("-device", "device_add", "device_del", "id"),
+    ("-blockdev", "blockdev-add", "blockdev-del", "node-name"),
+    ("-netdev", "netdev_add", "netdev_del", "id"),
+];
+
+fn hotplug_entry(option: &str) -> Result<&'static HotplugEntry, QmpHotplugError> {
+    HOTPLUG_OPTIONS
+        .iter()
+        .find(|(o, ..)| *o == option)
+        .ok_or_else(|| QmpHotplugError::UnsupportedOption(option.to_string()))
+}
+
+impl QmpCommand {
+    /// Builds the `device_add` / `blockdev-add` / `netdev_add` command that plugs in
+    /// what a `-device` / `-blockdev` / `-netdev` launch argument describes.
+    pub fn hotplug_from_arg(arg: &QemuArg) -> Result<Self, QmpHotplugError> {
+        let (option, add, _, id_key) = hotplug_entry(arg.key())?;
+        let Some(QemuArg::Json(_, arguments)) = arg.to_json_syntax() else {
+            return Err(QmpHotplugError::InvalidArgument(format!(
+                "{} argument is not a property list or repeats a key",
+                option
+            )));
+        };
+        if arguments.get(*id_key).is_none() {
+            return Err(QmpHotplugError::MissingId(option.to_string()));
+        }
+        Ok(QmpCommand::new(*add).with_arguments(arguments))
+    }
+
+    /// Builds the `device_del` / `blockdev-del` / `netdev_del` command that removes
+    /// what a `-device` / `-blockdev` / `-netdev` launch argument describes.
+    pub fn unplug_from_arg(arg: &QemuArg) -> Result<Self, QmpHotplugError> {
+        let (option, _, del, id_key) = hotplug_entry(arg.key())?;
+        let id = arg
+            .get_property(id_key)
+            .ok_or_else(|| QmpHotplugError::MissingId(option.to_string()))?;
+        Ok(QmpCommand::new(*del).with_arguments(json!({ *id_key: id })))
+    }
+
+    /// Turns a `device_add` / `blockdev-add` / `netdev_add` command back into the
+    /// launch argument that creates the same object at start-up.
+    pub fn to_launch_arg(&self) -> Result<QemuArg, QmpHotplugError> {
+        let (option, ..) = HOTPLUG_OPTIONS
+            .iter()
+            .find(|(_, add, ..)| *add == self.execute)
+            .ok_or_else(|| QmpHotplugError::UnsupportedCommand(self.execute.clone()))?;
+        match &self.arguments {
+            Some(arguments @ Value::Object(_)) => {
+                Ok(QemuArg::from_json(*option, arguments.clone()))
+            }
+            _ => Err(QmpHotplugError::InvalidArgument(format!(
+                "{} has no argument object",
+                self.execute
+            ))),
+        }
+    }
+}
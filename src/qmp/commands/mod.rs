@@ -1,8 +1,13 @@
 mod command_impls;
+mod hotplug;
 mod qmp_command;
+mod qmp_execute_error;
+mod qmp_hotplug_error;
 mod qmp_send_error;
 mod qmp_sender;
 
 pub use qmp_command::QmpCommand;
+pub use qmp_execute_error::QmpExecuteError;
+pub use qmp_hotplug_error::QmpHotplugError;
 pub use qmp_send_error::QmpSendError;
 pub use qmp_sender::QmpSender;
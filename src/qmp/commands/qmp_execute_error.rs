@@ -0,0 +1,39 @@
+use super::QmpSendError;
+use crate::qmp::messages::QmpError;
+
+#[derive(Debug)]
+pub enum QmpExecuteError {
+    Send(QmpSendError),
+    Command(Box<QmpError>),
+    Disconnected,
+    Timeout,
+}
+
+impl std::fmt::Display for QmpExecuteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QmpExecuteError::Send(e) => write!(f, "Send error: {}", e),
+            QmpExecuteError::Command(e) => write!(f, "QMP error {}: {}", e.class(), e.desc()),
+            QmpExecuteError::Disconnected => write!(f, "QMP stream closed"),
+            QmpExecuteError::Timeout => write!(f, "Timed out waiting for QMP"),
+        }
+    }
+}
+
+impl std::error::Error for QmpExecuteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QmpExecuteError::Send(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<QmpSendError> for QmpExecuteError {
+    fn from(e: QmpSendError) -> Self {
+        match e {
+            QmpSendError::NotConnected => QmpExecuteError::Disconnected,
+            e => QmpExecuteError::Send(e),
+        }
+    }
+}
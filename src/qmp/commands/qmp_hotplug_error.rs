@@ -0,0 +1,41 @@
+use super::QmpExecuteError;
+
+#[derive(Debug)]
+pub enum QmpHotplugError {
+    UnsupportedOption(String),
+    UnsupportedCommand(String),
+    MissingId(String),
+    InvalidArgument(String),
+    Execute(QmpExecuteError),
+}
+
+impl std::fmt::Display for QmpHotplugError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QmpHotplugError::UnsupportedOption(option) => {
+                write!(f, "{} cannot be hotplugged", option)
+            }
+            QmpHotplugError::UnsupportedCommand(command) => {
+                write!(f, "{} has no launch argument equivalent", command)
+            }
+            QmpHotplugError::MissingId(option) => write!(f, "{} argument has no id", option),
+            QmpHotplugError::InvalidArgument(reason) => write!(f, "Invalid argument: {}", reason),
+            QmpHotplugError::Execute(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for QmpHotplugError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QmpHotplugError::Execute(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<QmpExecuteError> for QmpHotplugError {
+    fn from(e: QmpExecuteError) -> Self {
+        QmpHotplugError::Execute(e)
+    }
+}
@@ -125,17 +125,17 @@ impl QmpDispatcher {
                 }
             }
             QmpMessage::Reply(rep) => {
-                if let Some(id) = &rep.id {
-                    if let Some(handler) = self.reply_handlers.get(&id) {
-                        handler(rep);
-                    }
+                if let Some(id) = &rep.id
+                    && let Some(handler) = self.reply_handlers.get(id)
+                {
+                    handler(rep);
                 }
             }
             QmpMessage::Error(err) => {
-                if let Some(id) = &err.id {
-                    if let Some(handler) = self.error_handlers.get(&id) {
-                        handler(err);
-                    }
+                if let Some(id) = &err.id
+                    && let Some(handler) = self.error_handlers.get(id)
+                {
+                    handler(err);
                 }
             }
             QmpMessage::Unknown(u) => {
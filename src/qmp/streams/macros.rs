@@ -16,12 +16,12 @@ macro_rules! define_filtered_qmp_stream {
             S: ::tokio::io::AsyncRead + ::core::marker::Unpin + Send + 'static,
         {
             pub fn from_message_stream(
-                stream: crate::qmp::streams::message_stream::QmpMessageStream<S>,
+                stream: $crate::qmp::streams::message_stream::QmpMessageStream<S>,
             ) -> Self {
                 use ::futures::StreamExt as _;
                 let filtered = stream.filter_map(|msg| async move {
                     match msg {
-                        crate::qmp::messages::QmpMessage::$variant(v) => Some(v),
+                        $crate::qmp::messages::QmpMessage::$variant(v) => Some(v),
                         _ => None,
                     }
                 });
@@ -33,7 +33,7 @@ macro_rules! define_filtered_qmp_stream {
 
             pub fn from_reader(reader: S, cancel: ::tokio_util::sync::CancellationToken) -> Self {
                 let base =
-                    crate::qmp::streams::message_stream::QmpMessageStream::new(reader, cancel);
+                    $crate::qmp::streams::message_stream::QmpMessageStream::new(reader, cancel);
                 Self::from_message_stream(base)
             }
         }
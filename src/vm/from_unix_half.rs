@@ -0,0 +1,32 @@
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+
+/// Converts one half of a Unix socket into the reader/writer type a `VmController` is
+/// parameterised over, so a QMP monitor provisioned at launch can be attached to it.
+pub trait FromUnixHalf<H>: Sized {
+    fn from_unix_half(half: H) -> Self;
+}
+
+impl FromUnixHalf<OwnedReadHalf> for OwnedReadHalf {
+    fn from_unix_half(half: OwnedReadHalf) -> Self {
+        half
+    }
+}
+
+impl FromUnixHalf<OwnedWriteHalf> for OwnedWriteHalf {
+    fn from_unix_half(half: OwnedWriteHalf) -> Self {
+        half
+    }
+}
+
+impl FromUnixHalf<OwnedReadHalf> for Box<dyn AsyncRead + Unpin + Send + 'static> {
+    fn from_unix_half(half: OwnedReadHalf) -> Self {
+        Box::new(half)
+    }
+}
+
+impl FromUnixHalf<OwnedWriteHalf> for Box<dyn AsyncWrite + Unpin + Send + 'static> {
+    fn from_unix_half(half: OwnedWriteHalf) -> Self {
+        Box::new(half)
+    }
+}
@@ -0,0 +1,175 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+
+use crate::launcher::QemuOutputLine;
+
+/// How long to wait for a `guest-ping` reply before asking again.
+const GUEST_PING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often to retry connecting to a chardev socket QEMU has not created yet.
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Bytes of serial output kept while looking for a pattern that spans reads.
+const SERIAL_WINDOW_BYTES: usize = 4096;
+
+/// A signal that the guest, not just QEMU, is up.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum GuestReadiness {
+    /// Text on a serial console exposed as a unix socket chardev
+    /// (e.g. `-serial unix:PATH,server=on,wait=off`).
+    SerialPattern { socket: PathBuf, pattern: String },
+    /// Text in QEMU's captured stdout/stderr, e.g. with `-serial stdio`. Needs the
+    /// stream to be launched with `QemuStdio::Pipe`.
+    OutputPattern(String),
+    /// A reply to `guest-ping` from the QEMU guest agent on its chardev socket.
+    GuestAgent { socket: PathBuf },
+    /// A line on a virtio-serial port's chardev socket; any line if `message` is `None`.
+    VirtioSerial {
+        socket: PathBuf,
+        message: Option<String>,
+    },
+}
+
+impl GuestReadiness {
+    pub fn serial_pattern(socket: impl Into<PathBuf>, pattern: impl Into<String>) -> Self {
+        Self::SerialPattern {
+            socket: socket.into(),
+            pattern: pattern.into(),
+        }
+    }
+
+    pub fn output_pattern(pattern: impl Into<String>) -> Self {
+        Self::OutputPattern(pattern.into())
+    }
+
+    pub fn guest_agent(socket: impl Into<PathBuf>) -> Self {
+        Self::GuestAgent {
+            socket: socket.into(),
+        }
+    }
+
+    pub fn virtio_serial(socket: impl Into<PathBuf>, message: Option<String>) -> Self {
+        Self::VirtioSerial {
+            socket: socket.into(),
+            message,
+        }
+    }
+
+    /// Waits for the signal without a deadline; the caller bounds it. `history` and
+    /// `output` are the captured QEMU output so far and from now on.
+    pub(crate) async fn wait(
+        &self,
+        history: Vec<QemuOutputLine>,
+        output: Option<impl Stream<Item = QemuOutputLine> + Unpin>,
+    ) -> std::io::Result<()> {
+        match self {
+            GuestReadiness::SerialPattern { socket, pattern } => {
+                wait_serial_pattern(socket, pattern).await
+            }
+            GuestReadiness::OutputPattern(pattern) => {
+                if history.iter().any(|l| l.line.contains(pattern.as_str())) {
+                    return Ok(());
+                }
+                let Some(mut output) = output else {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "waiting for an output pattern needs piped stdout or stderr",
+                    ));
+                };
+                while let Some(line) = output.next().await {
+                    if line.line.contains(pattern.as_str()) {
+                        return Ok(());
+                    }
+                }
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "QEMU output closed before the pattern appeared",
+                ))
+            }
+            GuestReadiness::GuestAgent { socket } => wait_guest_agent(socket).await,
+            GuestReadiness::VirtioSerial { socket, message } => {
+                let mut lines = BufReader::new(connect_chardev(socket).await?).lines();
+                while let Some(line) = lines.next_line().await? {
+                    if message.as_ref().is_none_or(|m| line.contains(m.as_str())) {
+                        return Ok(());
+                    }
+                }
+                Err(closed(socket))
+            }
+        }
+    }
+}
+
+fn closed(socket: &Path) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::UnexpectedEof,
+        format!("{} closed before the guest was ready", socket.display()),
+    )
+}
+
+/// Connects to a chardev socket, retrying while QEMU has not created it yet.
+async fn connect_chardev(socket: &Path) -> std::io::Result<UnixStream> {
+    loop {
+        match UnixStream::connect(socket).await {
+            Ok(stream) => return Ok(stream),
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused
+                ) => {}
+            Err(e) => return Err(e),
+        }
+        tokio::time::sleep(CONNECT_RETRY_INTERVAL).await;
+    }
+}
+
+async fn wait_serial_pattern(socket: &Path, pattern: &str) -> std::io::Result<()> {
+    let mut stream = connect_chardev(socket).await?;
+    let mut window = String::new();
+    let mut buf = [0u8; 1024];
+    loop {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Err(closed(socket));
+        }
+        window.push_str(&String::from_utf8_lossy(&buf[..n]));
+        if window.contains(pattern) {
+            return Ok(());
+        }
+        let keep = SERIAL_WINDOW_BYTES.max(pattern.len());
+        if window.len() > keep {
+            let mut cut = window.len() - keep;
+            while !window.is_char_boundary(cut) {
+                cut += 1;
+            }
+            window.drain(..cut);
+        }
+    }
+}
+
+async fn wait_guest_agent(socket: &Path) -> std::io::Result<()> {
+    let (r, mut w) = connect_chardev(socket).await?.into_split();
+    let mut lines = BufReader::new(r).lines();
+    loop {
+        // 0xFF makes the agent drop any partial request left over from before it started.
+        w.write_all(b"\xff{\"execute\":\"guest-ping\"}\n").await?;
+        let deadline = tokio::time::Instant::now() + GUEST_PING_INTERVAL;
+        loop {
+            match tokio::time::timeout_at(deadline, lines.next_line()).await {
+                Err(_) => break,
+                Ok(Ok(Some(line))) => {
+                    let reply: Option<serde_json::Value> = serde_json::from_str(&line).ok();
+                    if reply.is_some_and(|r| r.get("return").is_some()) {
+                        return Ok(());
+                    }
+                }
+                Ok(Ok(None)) => return Err(closed(socket)),
+                Ok(Err(e)) => return Err(e),
+            }
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use std::time::Duration;
+
+use super::VmExit;
+
+/// Notifications emitted by `VmSupervisor`.
+#[derive(Debug, Clone)]
+pub enum VmLifecycleEvent {
+    Started { attempt: u32, pid: Option<u32> },
+    LaunchFailed { attempt: u32, error: String },
+    Exited { attempt: u32, exit: VmExit },
+    RestartScheduled { attempt: u32, delay: Duration },
+    GaveUp { reason: String },
+    Stopped,
+}
@@ -1,7 +1,27 @@
+mod from_unix_half;
+mod guest_readiness;
+mod lifecycle_event;
+mod qmp_monitor;
+mod shutdown_policy;
+mod supervisor_config;
 mod vm_controller;
+mod vm_exit;
 mod vm_instance;
 mod vm_manager;
+mod vm_readiness;
+mod vm_ready_error;
+mod vm_supervisor;
 
+pub use from_unix_half::FromUnixHalf;
+pub use guest_readiness::GuestReadiness;
+pub use lifecycle_event::VmLifecycleEvent;
+pub use qmp_monitor::QmpMonitorConfig;
+pub use shutdown_policy::{ShutdownOutcome, ShutdownPolicy, ShutdownStep};
+pub use supervisor_config::{RestartPolicy, SupervisorConfig};
 pub use vm_controller::VmController;
+pub use vm_exit::{VmExit, VmExitKind};
 pub use vm_instance::VmInstance;
 pub use vm_manager::VmManager;
+pub use vm_readiness::VmReadiness;
+pub use vm_ready_error::{VmReadyError, VmReadyStage};
+pub use vm_supervisor::{VmSupervisor, VmSupervisorOutcome};
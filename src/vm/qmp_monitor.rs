@@ -0,0 +1,223 @@
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::net::UnixStream;
+
+use super::VmReadyError;
+use crate::launcher::options::{QemuChardev, QemuOptionError};
+use crate::launcher::{QemuLaunchArgs, QemuProcess};
+
+/// Lines of captured stderr included when QEMU dies before the socket is up.
+const STDERR_TAIL_LINES: usize = 20;
+
+static NEXT_MONITOR_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Settings for the QMP monitor that `VmInstance::launch` provisions on its own.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct QmpMonitorConfig {
+    runtime_dir: PathBuf,
+    chardev_id: String,
+    connect_timeout: Duration,
+}
+
+impl Default for QmpMonitorConfig {
+    fn default() -> Self {
+        let base = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        Self {
+            runtime_dir: base.join("qemu-lite-wrapper"),
+            chardev_id: "qlw-qmp".to_string(),
+            connect_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl QmpMonitorConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_runtime_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.runtime_dir = dir.into();
+        self
+    }
+
+    pub fn with_chardev_id(mut self, id: impl Into<String>) -> Self {
+        self.chardev_id = id.into();
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    pub fn get_runtime_dir(&self) -> &Path {
+        &self.runtime_dir
+    }
+
+    pub fn get_chardev_id(&self) -> &str {
+        &self.chardev_id
+    }
+
+    pub fn get_connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+}
+
+/// A QMP socket owned by one VM launch. The socket file is left in place while QEMU
+/// may still be running (so it can be attached to later) and removed with `remove`.
+#[derive(Debug)]
+pub(crate) struct QmpMonitor {
+    socket_path: PathBuf,
+    connect_timeout: Duration,
+}
+
+impl QmpMonitor {
+    /// Allocates a fresh socket path inside the runtime directory. The directory is
+    /// made private; an existing one must belong to the current user.
+    ///
+    /// When QEMU runs as another `uid`, the socket goes into a `uid-<uid>`
+    /// subdirectory handed to that uid (and `gid`), and the runtime directory is made
+    /// searchable (but not listable) so QEMU can reach it.
+    pub(crate) fn allocate(
+        config: &QmpMonitorConfig,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> std::io::Result<Self> {
+        // SAFETY: geteuid has no preconditions.
+        let euid = unsafe { libc::geteuid() };
+        let mut dir = config.runtime_dir.clone();
+        if let Some(parent) = dir.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        match uid.filter(|&uid| uid != euid) {
+            None => {
+                prepare_dir(&dir, 0o700, euid)?;
+            }
+            Some(uid) => {
+                prepare_dir(&dir, 0o711, euid)?;
+                dir.push(format!("uid-{}", uid));
+                // An existing subdirectory must already belong to `uid`; a new one is handed over.
+                if prepare_dir(&dir, 0o700, uid)? {
+                    std::os::unix::fs::chown(&dir, Some(uid), gid).map_err(|e| {
+                        let _ = std::fs::remove_dir(&dir);
+                        std::io::Error::new(
+                            e.kind(),
+                            format!(
+                                "cannot hand QMP runtime directory {} to uid {}: {}",
+                                dir.display(),
+                                uid,
+                                e
+                            ),
+                        )
+                    })?;
+                }
+            }
+        }
+
+        let id = NEXT_MONITOR_ID.fetch_add(1, Ordering::Relaxed);
+        let socket_path = dir.join(format!("qmp-{}-{}.sock", std::process::id(), id));
+        match std::fs::remove_file(&socket_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+
+        Ok(Self {
+            socket_path,
+            connect_timeout: config.connect_timeout,
+        })
+    }
+
+    pub(crate) fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// Returns a copy of `args` with a `-chardev socket` / `-mon mode=control` pair appended.
+    pub(crate) fn inject_into(
+        &self,
+        args: &QemuLaunchArgs,
+        chardev_id: &str,
+    ) -> Result<QemuLaunchArgs, QemuOptionError> {
+        let chardev =
+            QemuChardev::unix_server(chardev_id, self.socket_path.to_string_lossy()).to_arg()?;
+        Ok(args.clone().with_arg(chardev).with_list(
+            "-mon",
+            vec![
+                format!("chardev={}", chardev_id),
+                "mode=control".to_string(),
+            ],
+        ))
+    }
+
+    /// Polls the socket until QEMU accepts a connection, the process exits, or the timeout hits.
+    pub(crate) async fn connect(
+        &self,
+        process: &mut QemuProcess,
+    ) -> Result<UnixStream, VmReadyError> {
+        let deadline = tokio::time::Instant::now() + self.connect_timeout;
+        loop {
+            if let Some(status) = process.try_wait_status() {
+                let stderr = process.stderr_tail(STDERR_TAIL_LINES).await;
+                return Err(VmReadyError::Exited { status, stderr });
+            }
+            match UnixStream::connect(&self.socket_path).await {
+                Ok(stream) => return Ok(stream),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused
+                    ) => {}
+                Err(e) => return Err(e.into()),
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(VmReadyError::Launch(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!(
+                        "timed out waiting for QMP socket {}",
+                        self.socket_path.display()
+                    ),
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    /// Removes the socket file; only call this once QEMU has exited.
+    pub(crate) fn remove(self) {
+        let _ = std::fs::remove_file(&self.socket_path);
+    }
+}
+
+/// Creates `dir` with `mode`, or checks that an existing `dir` is a directory that
+/// belongs to `owner` and resets its mode to `mode`, so group and other access is
+/// dropped. Returns whether the directory was created.
+fn prepare_dir(dir: &Path, mode: u32, owner: u32) -> std::io::Result<bool> {
+    match std::fs::DirBuilder::new().mode(mode).create(dir) {
+        Ok(()) => {
+            std::fs::set_permissions(dir, std::fs::Permissions::from_mode(mode))?;
+            Ok(true)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            let metadata = std::fs::symlink_metadata(dir)?;
+            if !metadata.is_dir() || metadata.uid() != owner {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::PermissionDenied,
+                    format!(
+                        "QMP runtime directory {} is not a directory owned by uid {}",
+                        dir.display(),
+                        owner
+                    ),
+                ));
+            }
+            if metadata.mode() & 0o7777 != mode {
+                std::fs::set_permissions(dir, std::fs::Permissions::from_mode(mode))?;
+            }
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
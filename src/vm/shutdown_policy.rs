@@ -0,0 +1,78 @@
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use super::VmExit;
+use crate::qmp::messages::QmpEvent;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum ShutdownStep {
+    /// ACPI power button via QMP `system_powerdown`; the guest shuts itself down.
+    Powerdown,
+    /// QMP `quit`.
+    Quit,
+    Sigterm,
+    Sigkill,
+}
+
+/// Ordered escalation steps, each with the time QEMU is given to exit afterwards.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct ShutdownPolicy {
+    steps: Vec<(ShutdownStep, Duration)>,
+}
+
+impl Default for ShutdownPolicy {
+    fn default() -> Self {
+        Self::empty()
+            .with_step(ShutdownStep::Powerdown, Duration::from_secs(30))
+            .with_step(ShutdownStep::Quit, Duration::from_secs(10))
+            .with_step(ShutdownStep::Sigterm, Duration::from_secs(10))
+            .with_step(ShutdownStep::Sigkill, Duration::from_secs(5))
+    }
+}
+
+impl ShutdownPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A policy with no steps; build one up with `with_step`.
+    pub fn empty() -> Self {
+        Self { steps: Vec::new() }
+    }
+
+    /// Only SIGKILL, i.e. the old `terminate` behaviour.
+    pub fn immediate() -> Self {
+        Self::empty().with_step(ShutdownStep::Sigkill, Duration::from_secs(5))
+    }
+
+    pub fn with_step(mut self, step: ShutdownStep, timeout: Duration) -> Self {
+        self.steps.push((step, timeout));
+        self
+    }
+
+    /// Changes the timeout of `step` if it is part of the policy.
+    pub fn with_timeout(mut self, step: ShutdownStep, timeout: Duration) -> Self {
+        for (s, t) in &mut self.steps {
+            if *s == step {
+                *t = timeout;
+            }
+        }
+        self
+    }
+
+    pub fn get_steps(&self) -> &[(ShutdownStep, Duration)] {
+        &self.steps
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShutdownOutcome {
+    /// The step after which QEMU exited, or `None` if it was not running to begin with.
+    pub stopped_by: Option<ShutdownStep>,
+    pub exit_status: Option<ExitStatus>,
+    /// The `SHUTDOWN` event seen while shutting down, if any.
+    pub shutdown_event: Option<QmpEvent>,
+    /// Steps that were skipped or failed, with the reason.
+    pub skipped: Vec<(ShutdownStep, String)>,
+    pub exit: Option<VmExit>,
+}
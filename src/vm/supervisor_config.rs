@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum RestartPolicy {
+    Never,
+    /// Restart after failures (see `VmExitKind::is_failure`), at most `max_retries`
+    /// times in a row.
+    OnFailure {
+        max_retries: u32,
+    },
+    Always,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct SupervisorConfig {
+    restart_policy: RestartPolicy,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    /// A run lasting at least this long resets the backoff and the retry count.
+    stable_after: Duration,
+    crash_loop_window: Duration,
+    crash_loop_limit: usize,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            restart_policy: RestartPolicy::OnFailure { max_retries: 5 },
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            stable_after: Duration::from_secs(60),
+            crash_loop_window: Duration::from_secs(300),
+            crash_loop_limit: 10,
+        }
+    }
+}
+
+impl SupervisorConfig {
+    pub fn new(restart_policy: RestartPolicy) -> Self {
+        Self {
+            restart_policy,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart_policy = policy;
+        self
+    }
+
+    /// Delay before the first restart; doubled for every further consecutive restart.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn with_stable_after(mut self, stable_after: Duration) -> Self {
+        self.stable_after = stable_after;
+        self
+    }
+
+    /// Gives up once `limit` failures happen within `window`, whatever the policy.
+    pub fn with_crash_loop(mut self, window: Duration, limit: usize) -> Self {
+        self.crash_loop_window = window;
+        self.crash_loop_limit = limit;
+        self
+    }
+
+    pub fn get_restart_policy(&self) -> RestartPolicy {
+        self.restart_policy
+    }
+
+    pub fn get_initial_backoff(&self) -> Duration {
+        self.initial_backoff
+    }
+
+    pub fn get_max_backoff(&self) -> Duration {
+        self.max_backoff
+    }
+
+    pub fn get_stable_after(&self) -> Duration {
+        self.stable_after
+    }
+
+    pub fn get_crash_loop_window(&self) -> Duration {
+        self.crash_loop_window
+    }
+
+    pub fn get_crash_loop_limit(&self) -> usize {
+        self.crash_loop_limit
+    }
+
+    /// Backoff before restart number `restart` (1-based) of a consecutive series.
+    pub fn backoff_for(&self, restart: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(restart.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
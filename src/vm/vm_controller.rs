@@ -1,10 +1,26 @@
+use std::path::Path;
+use std::time::Duration;
+
+use futures::StreamExt;
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::UnixStream;
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio_util::sync::CancellationToken;
 
-use super::VmInstance;
+use super::{
+    FromUnixHalf, QmpMonitorConfig, ShutdownOutcome, ShutdownPolicy, ShutdownStep, VmExit,
+    VmInstance, VmReadiness, VmReadyError, VmReadyStage,
+};
 
-use crate::launcher::QemuLaunchArgs;
-use crate::qmp::commands::{QmpCommand, QmpSendError, QmpSender};
+use crate::launcher::json::QemuProfileVars;
+use crate::launcher::{
+    QemuArg, QemuLaunchArgs, QemuLaunchOptions, QemuPreflight, QemuProcess, read_pidfile,
+};
+use crate::qmp::commands::{QmpCommand, QmpExecuteError, QmpHotplugError, QmpSendError, QmpSender};
+use crate::qmp::dispatcher::QmpDispatcher;
+use crate::qmp::messages::{QmpEvent, QmpMessage, QmpReply};
 use crate::qmp::streams::QmpMessageStream;
+use crate::qmp::types::QmpId;
 
 pub struct VmController<R, W>
 where
@@ -12,10 +28,38 @@ where
     W: AsyncWrite + Unpin + Send + 'static,
 {
     instance: VmInstance,
+    /// Turns the provisioned QMP socket into `R`/`W`; set with the monitor.
+    split_qmp: Option<fn(UnixStream) -> (R, W)>,
     sender: Option<QmpSender<W>>,
     stream: Option<QmpMessageStream<R>>,
+    dispatcher: Option<QmpDispatcher>,
+    shutdown_policy: ShutdownPolicy,
+    qmp_timeout: Duration,
+    exit_event: Option<QmpEvent>,
+    last_exit: Option<VmExit>,
+    next_id: u64,
+}
+
+enum ExitWait {
+    Exited(std::process::ExitStatus),
+    GuestShutdown,
+    TimedOut,
 }
 
+/// Lines of captured stderr kept in a `VmExit`.
+const EXIT_STDERR_TAIL_LINES: usize = 20;
+
+fn is_exit_event(ev: &QmpEvent) -> bool {
+    ev.name == "SHUTDOWN" || ev.name == "GUEST_PANICKED"
+}
+
+/// How long `execute` waits for the reply to a command by default.
+const DEFAULT_QMP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long QEMU gets to exit after the guest reported `SHUTDOWN` during a powerdown.
+/// QEMU started with `-no-shutdown` stays alive, so the next step takes over after this.
+const SHUTDOWN_EVENT_GRACE: Duration = Duration::from_secs(2);
+
 impl<R, W> VmController<R, W>
 where
     R: AsyncRead + Unpin + Send + 'static,
@@ -24,11 +68,90 @@ where
     pub fn new(args: QemuLaunchArgs) -> Self {
         Self {
             instance: VmInstance::new(args),
+            split_qmp: None,
             sender: None,
             stream: None,
+            dispatcher: None,
+            shutdown_policy: ShutdownPolicy::default(),
+            qmp_timeout: DEFAULT_QMP_TIMEOUT,
+            exit_event: None,
+            last_exit: None,
+            next_id: 0,
         }
     }
 
+    pub fn with_launch_options(mut self, options: QemuLaunchOptions) -> Self {
+        *self.instance.get_mut_launch_options() = options;
+        self
+    }
+
+    pub fn with_shutdown_policy(mut self, policy: ShutdownPolicy) -> Self {
+        self.shutdown_policy = policy;
+        self
+    }
+
+    pub fn set_shutdown_policy(&mut self, policy: ShutdownPolicy) {
+        self.shutdown_policy = policy;
+    }
+
+    pub fn get_shutdown_policy(&self) -> &ShutdownPolicy {
+        &self.shutdown_policy
+    }
+
+    /// How long `execute` (and so `hotplug_device`) waits for a reply; 10 seconds by default.
+    pub fn with_qmp_timeout(mut self, timeout: Duration) -> Self {
+        self.qmp_timeout = timeout;
+        self
+    }
+
+    pub fn set_qmp_timeout(&mut self, timeout: Duration) {
+        self.qmp_timeout = timeout;
+    }
+
+    pub fn get_qmp_timeout(&self) -> Duration {
+        self.qmp_timeout
+    }
+
+    pub fn with_qmp_monitor(mut self, config: QmpMonitorConfig) -> Self
+    where
+        R: FromUnixHalf<OwnedReadHalf>,
+        W: FromUnixHalf<OwnedWriteHalf>,
+    {
+        self.set_qmp_monitor(Some(config));
+        self
+    }
+
+    pub fn set_qmp_monitor(&mut self, config: Option<QmpMonitorConfig>)
+    where
+        R: FromUnixHalf<OwnedReadHalf>,
+        W: FromUnixHalf<OwnedWriteHalf>,
+    {
+        self.split_qmp = Some(split_unix_stream::<R, W>);
+        self.instance.set_qmp_monitor(config);
+    }
+
+    pub fn qmp_socket_path(&self) -> Option<&Path> {
+        self.instance.qmp_socket_path()
+    }
+
+    pub fn with_preflight(mut self, preflight: QemuPreflight) -> Self {
+        self.instance.set_preflight(Some(preflight));
+        self
+    }
+
+    pub fn with_profile_vars(mut self, vars: QemuProfileVars) -> Self {
+        self.instance.set_profile_vars(Some(vars));
+        self
+    }
+
+    pub fn get_instance(&self) -> &VmInstance {
+        &self.instance
+    }
+
+    pub fn get_mut_instance(&mut self) -> &mut VmInstance {
+        &mut self.instance
+    }
+
     pub fn set_sender(&mut self, sender: Option<QmpSender<W>>) {
         self.sender = sender;
     }
@@ -49,15 +172,383 @@ where
         &mut self.stream
     }
 
+    /// Dispatcher that receives the messages `execute` and `wait_for_event` read
+    /// from the stream but are not waiting for.
+    pub fn set_dispatcher(&mut self, dispatcher: Option<QmpDispatcher>) {
+        self.dispatcher = dispatcher;
+    }
+    pub fn get_dispatcher(&self) -> &Option<QmpDispatcher> {
+        &self.dispatcher
+    }
+    pub fn get_mut_dispatcher(&mut self) -> &mut Option<QmpDispatcher> {
+        &mut self.dispatcher
+    }
+
+    /// Launches the VM. If a QMP monitor is configured, the capabilities handshake is
+    /// performed and the resulting sender and message stream are attached. A monitor
+    /// configured on the instance directly is left for `VmInstance::take_qmp_stream`.
     pub async fn launch(&mut self) -> std::io::Result<()> {
-        self.instance.launch().await
+        Ok(self.try_launch().await?)
+    }
+
+    /// `launch`, reporting QEMU exiting before the QMP socket is up as `Exited`.
+    async fn try_launch(&mut self) -> Result<(), VmReadyError> {
+        self.instance.try_launch().await?;
+        self.exit_event = None;
+        self.last_exit = None;
+
+        if self.split_qmp.is_none() {
+            return Ok(());
+        }
+        let Some(socket) = self.instance.take_qmp_stream() else {
+            return Ok(());
+        };
+        let timeout = self
+            .instance
+            .get_qmp_monitor()
+            .as_ref()
+            .map(|c| c.get_connect_timeout())
+            .unwrap_or_default();
+
+        if let Err(e) = self.attach_qmp(socket, timeout).await {
+            let _ = self.instance.kill().await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Launches the VM and waits until it is usable: QEMU is running, the QMP socket
+    /// (if a monitor is configured) has completed its handshake, and the guest signal
+    /// in `readiness` has been seen. Fails as soon as QEMU exits, with its stderr tail.
+    /// On any failure QEMU is killed.
+    pub async fn launch_and_wait_ready(
+        &mut self,
+        readiness: &VmReadiness,
+    ) -> Result<(), VmReadyError> {
+        let deadline = tokio::time::Instant::now() + readiness.get_deadline();
+
+        // Bound the QMP wait by the overall deadline instead of cancelling `launch`,
+        // which would leave the spawned QEMU behind.
+        let monitor = self.instance.get_qmp_monitor().clone();
+        if let Some(config) = &monitor {
+            let timeout = config.get_connect_timeout().min(readiness.get_deadline());
+            self.instance
+                .set_qmp_monitor(Some(config.clone().with_connect_timeout(timeout)));
+        }
+        let launched = self.try_launch().await;
+        self.instance.set_qmp_monitor(monitor);
+        match launched {
+            Ok(()) => {}
+            Err(VmReadyError::Launch(e)) if e.kind() == std::io::ErrorKind::TimedOut => {
+                return Err(VmReadyError::TimedOut(VmReadyStage::Qmp));
+            }
+            Err(e) => return Err(e),
+        }
+
+        let Err(e) = self.wait_guest_ready(readiness, deadline).await else {
+            return Ok(());
+        };
+        if let Some(process) = self.instance.get_mut_process()
+            && let Some(status) = process.try_wait_status()
+        {
+            let stderr = process.stderr_tail(EXIT_STDERR_TAIL_LINES).await;
+            self.close_qmp();
+            return Err(VmReadyError::Exited { status, stderr });
+        }
+        let _ = self.instance.kill().await;
+        self.close_qmp();
+        Err(e)
+    }
+
+    async fn wait_guest_ready(
+        &mut self,
+        readiness: &VmReadiness,
+        deadline: tokio::time::Instant,
+    ) -> Result<(), VmReadyError> {
+        // Subscribe before taking the snapshot so no line falls between the two; the
+        // lines that end up in both are skipped at the start of the stream.
+        let (history, output) = match self.instance.output() {
+            Some(output) => {
+                let lines = output.lines();
+                let history = output.snapshot();
+                let seen = history.clone();
+                let lines = lines.skip_while(move |line| std::future::ready(seen.contains(line)));
+                (history, Some(Box::pin(lines)))
+            }
+            None => (Vec::new(), None),
+        };
+        let has_qmp = self.sender.is_some();
+        let guest = async {
+            match readiness.get_guest() {
+                Some(guest) => guest.wait(history, output).await,
+                None if has_qmp => Ok(()),
+                None => {
+                    tokio::time::sleep(readiness.get_settle()).await;
+                    Ok(())
+                }
+            }
+        };
+        tokio::pin!(guest);
+
+        let mut poll = tokio::time::interval(Duration::from_millis(50));
+        loop {
+            tokio::select! {
+                result = &mut guest => return result.map_err(VmReadyError::Guest),
+                _ = tokio::time::sleep_until(deadline) => {
+                    return Err(VmReadyError::TimedOut(VmReadyStage::Guest));
+                }
+                _ = poll.tick() => {
+                    // Reported as `Exited` by the caller.
+                    if !self.instance.is_running() {
+                        return Err(VmReadyError::TimedOut(VmReadyStage::Guest));
+                    }
+                }
+            }
+        }
     }
 
+    /// Regains control of a QEMU process that was launched earlier (typically with
+    /// `-daemonize`/`-pidfile`) by a process that no longer exists.
+    ///
+    /// The PID must be alive and its `/proc/<pid>/cmdline` must look like QEMU; the
+    /// launch args are rebuilt from that command line, minus the monitor serving
+    /// `qmp_socket`. Exit is monitored without owning a child, so the
+    /// exit status is not available and `VmExit` relies on the QMP events.
+    pub async fn attach(
+        pidfile: impl AsRef<Path>,
+        qmp_socket: impl AsRef<Path>,
+    ) -> std::io::Result<Self>
+    where
+        R: FromUnixHalf<OwnedReadHalf>,
+        W: FromUnixHalf<OwnedWriteHalf>,
+    {
+        let qmp_socket = qmp_socket.as_ref();
+        let pid = read_pidfile(pidfile).await?;
+        let process = QemuProcess::adopt(pid)?;
+        let args = read_qemu_cmdline(pid, qmp_socket).await?;
+
+        let socket = UnixStream::connect(qmp_socket).await?;
+        let mut controller = Self::new(QemuLaunchArgs::default());
+        controller.instance = VmInstance::from_process(args, process);
+        controller.split_qmp = Some(split_unix_stream::<R, W>);
+        controller
+            .attach_qmp(socket, QmpMonitorConfig::default().get_connect_timeout())
+            .await?;
+        Ok(controller)
+    }
+
+    async fn attach_qmp(&mut self, socket: UnixStream, timeout: Duration) -> std::io::Result<()> {
+        let split = self
+            .split_qmp
+            .ok_or_else(|| std::io::Error::other("no QMP monitor configured"))?;
+        let (r, w) = split(socket);
+        let mut sender = QmpSender::new(w);
+        let mut stream = QmpMessageStream::new(r, CancellationToken::new());
+        tokio::time::timeout(timeout, qmp_handshake(&mut sender, &mut stream))
+            .await
+            .unwrap_or_else(|_| {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "timed out during QMP handshake",
+                ))
+            })?;
+
+        self.sender = Some(sender);
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    /// Stops the VM using the controller's shutdown policy.
     pub async fn terminate(&mut self) -> std::io::Result<()> {
+        self.shutdown().await.map(|_| ())
+    }
+
+    pub async fn shutdown(&mut self) -> std::io::Result<ShutdownOutcome> {
+        let policy = self.shutdown_policy.clone();
+        self.shutdown_with(&policy).await
+    }
+
+    /// Walks through the steps of `policy` until QEMU exits. Steps that need QMP are
+    /// skipped when no sender is attached. Fails if QEMU is still running afterwards.
+    pub async fn shutdown_with(
+        &mut self,
+        policy: &ShutdownPolicy,
+    ) -> std::io::Result<ShutdownOutcome> {
+        let mut outcome = ShutdownOutcome {
+            stopped_by: None,
+            exit_status: None,
+            shutdown_event: None,
+            skipped: Vec::new(),
+            exit: None,
+        };
+
+        if !self.instance.is_running() {
+            if self.instance.get_process().is_some() {
+                let status = self.instance.wait().await?;
+                outcome.exit_status = Some(status);
+                outcome.exit = Some(self.finish_exit(status, None).await);
+            }
+            self.close_qmp();
+            return Ok(outcome);
+        }
+
+        for &(step, timeout) in policy.get_steps() {
+            let action = match step {
+                ShutdownStep::Powerdown => self.system_powerdown().await.map_err(|e| e.to_string()),
+                ShutdownStep::Quit => self.quit().await.map_err(|e| e.to_string()),
+                ShutdownStep::Sigterm => self.signal_process(libc::SIGTERM),
+                ShutdownStep::Sigkill => self.signal_process(libc::SIGKILL),
+            };
+            if let Err(reason) = action {
+                outcome.skipped.push((step, reason));
+                continue;
+            }
+
+            let mut wait = self.wait_exit_or_shutdown(Some(timeout), true).await?;
+            if matches!(wait, ExitWait::GuestShutdown) {
+                wait = self
+                    .wait_exit_or_shutdown(Some(SHUTDOWN_EVENT_GRACE), false)
+                    .await?;
+            }
+            match wait {
+                ExitWait::Exited(status) => {
+                    outcome.stopped_by = Some(step);
+                    outcome.exit_status = Some(status);
+                    outcome.exit = Some(self.finish_exit(status, Some(step)).await);
+                    break;
+                }
+                _ => outcome
+                    .skipped
+                    .push((step, format!("QEMU still running after {:?}", timeout))),
+            }
+        }
+
+        outcome.shutdown_event = self.exit_event.clone().filter(|ev| ev.name == "SHUTDOWN");
+        if outcome.exit_status.is_none() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "VM still running after every shutdown step",
+            ));
+        }
+        // Reap through the instance so the provisioned QMP socket is cleaned up.
+        let _ = self.instance.wait().await;
+        self.close_qmp();
+        Ok(outcome)
+    }
+
+    fn signal_process(&self, signal: i32) -> Result<(), String> {
+        match self.instance.get_process() {
+            Some(p) => p.send_signal(signal).map_err(|e| e.to_string()),
+            None => Err("VM is not running".to_string()),
+        }
+    }
+
+    fn close_qmp(&mut self) {
         if let Some(stream) = &self.stream {
             stream.cancel();
         }
-        self.instance.terminate().await
+        self.stream = None;
+        self.sender = None;
+        self.instance.release_qmp();
+    }
+
+    /// Waits for QEMU to exit while draining the QMP stream, and classifies the exit.
+    pub async fn wait_exit(&mut self) -> std::io::Result<VmExit> {
+        loop {
+            if let ExitWait::Exited(status) = self.wait_exit_or_shutdown(None, false).await? {
+                let _ = self.instance.wait().await;
+                self.close_qmp();
+                return Ok(self.finish_exit(status, None).await);
+            }
+        }
+    }
+
+    /// Non-blocking check for a QEMU process that has exited on its own. Returns the
+    /// classified exit the first time it is noticed, `None` while QEMU runs or once reported.
+    pub async fn poll_exit(&mut self) -> Option<VmExit> {
+        if self.last_exit.is_some() {
+            return None;
+        }
+        let status = self
+            .instance
+            .get_mut_process()
+            .as_mut()?
+            .try_wait_status()?;
+        let _ = self.instance.wait().await;
+        self.close_qmp();
+        Some(self.finish_exit(status, None).await)
+    }
+
+    /// How the last QEMU process of this controller ended, once that is known.
+    pub fn last_exit(&self) -> Option<&VmExit> {
+        self.last_exit.as_ref()
+    }
+
+    async fn finish_exit(
+        &mut self,
+        status: std::process::ExitStatus,
+        requested: Option<ShutdownStep>,
+    ) -> VmExit {
+        let stderr_tail = match self.instance.get_mut_process() {
+            Some(p) => p.stderr_tail(EXIT_STDERR_TAIL_LINES).await,
+            None => Vec::new(),
+        };
+        // An adopted process has an unknown, unsuccessful status, so without an event
+        // or a request of ours its exit is classified as a crash.
+        let exit = VmExit::classify(status, self.exit_event.clone(), stderr_tail, requested);
+        self.last_exit = Some(exit.clone());
+        exit
+    }
+
+    /// Waits (up to `timeout`, if given) for QEMU to exit while draining the QMP stream.
+    /// With `return_on_shutdown`, returns early with `GuestShutdown` when a `SHUTDOWN`
+    /// event arrives.
+    async fn wait_exit_or_shutdown(
+        &mut self,
+        timeout: Option<Duration>,
+        return_on_shutdown: bool,
+    ) -> std::io::Result<ExitWait> {
+        let Some(process) = self.instance.get_mut_process().as_mut() else {
+            return Err(std::io::Error::other("VM is not running"));
+        };
+        let deadline = timeout.map(|t| tokio::time::Instant::now() + t);
+        let mut stream = self.stream.as_mut();
+        loop {
+            let next_message = async {
+                match stream.as_mut() {
+                    Some(s) => s.next().await,
+                    None => std::future::pending().await,
+                }
+            };
+            let expired = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                status = process.wait() => return Ok(ExitWait::Exited(status?)),
+                message = next_message => match message {
+                    Some(QmpMessage::Event(ev)) if is_exit_event(&ev) => {
+                        let shutdown = ev.name == "SHUTDOWN";
+                        if let Some(dispatcher) = &self.dispatcher {
+                            dispatcher.dispatch(&QmpMessage::Event(ev.clone()));
+                        }
+                        self.exit_event = Some(ev);
+                        if shutdown && return_on_shutdown {
+                            return Ok(ExitWait::GuestShutdown);
+                        }
+                    }
+                    Some(other) => {
+                        if let Some(dispatcher) = &self.dispatcher {
+                            dispatcher.dispatch(&other);
+                        }
+                    }
+                    None => stream = None,
+                },
+                _ = expired => return Ok(ExitWait::TimedOut),
+            }
+        }
     }
 
     pub fn message_stream(&mut self) -> Option<&mut QmpMessageStream<R>> {
@@ -71,6 +562,143 @@ where
         }
     }
 
+    /// Reads the next message from the attached stream. Use this rather than reading
+    /// `message_stream()` directly so `SHUTDOWN`/`GUEST_PANICKED` events are remembered
+    /// for exit classification.
+    pub async fn next_message(&mut self) -> Option<QmpMessage> {
+        self.read_message().await.ok()
+    }
+
+    async fn read_message(&mut self) -> Result<QmpMessage, QmpExecuteError> {
+        let message = match &mut self.stream {
+            Some(stream) => stream.next().await.ok_or(QmpExecuteError::Disconnected)?,
+            None => return Err(QmpExecuteError::Disconnected),
+        };
+        if let QmpMessage::Event(ev) = &message
+            && is_exit_event(ev)
+        {
+            self.exit_event = Some(ev.clone());
+        }
+        Ok(message)
+    }
+
+    fn dispatch(&self, message: &QmpMessage) {
+        if let Some(dispatcher) = &self.dispatcher {
+            dispatcher.dispatch(message);
+        }
+    }
+
+    fn assign_id(&mut self, cmd: QmpCommand) -> (QmpCommand, QmpId) {
+        match cmd.id.clone() {
+            Some(id) => (cmd, id),
+            None => {
+                self.next_id += 1;
+                let id = QmpId::Str(format!("qlw-{}", self.next_id));
+                (cmd.with_id(id.clone()), id)
+            }
+        }
+    }
+
+    /// Sends `cmd` and reads the message stream until its reply arrives, failing with
+    /// `Timeout` after the QMP timeout. An id is assigned if the command has none.
+    /// Other messages go to the dispatcher.
+    pub async fn execute(&mut self, cmd: QmpCommand) -> Result<QmpReply, QmpExecuteError> {
+        let (cmd, id) = self.assign_id(cmd);
+        let timeout = self.qmp_timeout;
+        tokio::time::timeout(timeout, async {
+            self.send_command(&cmd).await?;
+            loop {
+                match self.read_message().await? {
+                    QmpMessage::Reply(rep) if rep.id.as_ref() == Some(&id) => return Ok(rep),
+                    QmpMessage::Error(err) if err.id.as_ref() == Some(&id) => {
+                        return Err(QmpExecuteError::Command(Box::new(err)));
+                    }
+                    other => self.dispatch(&other),
+                }
+            }
+        })
+        .await
+        .unwrap_or(Err(QmpExecuteError::Timeout))
+    }
+
+    /// Reads the message stream until an event matching `predicate` arrives.
+    /// Other messages go to the dispatcher.
+    pub async fn wait_for_event<F>(&mut self, predicate: F) -> Result<QmpEvent, QmpExecuteError>
+    where
+        F: Fn(&QmpEvent) -> bool,
+    {
+        loop {
+            match self.read_message().await? {
+                QmpMessage::Event(ev) if predicate(&ev) => return Ok(ev),
+                other => self.dispatch(&other),
+            }
+        }
+    }
+
+    /// Hotplugs the object a `-device`, `-blockdev` or `-netdev` argument describes and
+    /// records the argument in the launch args, so a restart brings it back.
+    pub async fn hotplug_device(&mut self, arg: &QemuArg) -> Result<(), QmpHotplugError> {
+        let cmd = QmpCommand::hotplug_from_arg(arg)?;
+        self.execute(cmd).await?;
+        self.instance
+            .get_mut_args()
+            .get_mut_args()
+            .push(arg.clone());
+        Ok(())
+    }
+
+    /// Removes the object a `-device`, `-blockdev` or `-netdev` argument describes and
+    /// drops the argument from the launch args. For `-device`, this waits (up to
+    /// `timeout`) for the guest to release it, signalled by `DEVICE_DELETED`.
+    pub async fn unplug_device(
+        &mut self,
+        arg: &QemuArg,
+        timeout: Duration,
+    ) -> Result<(), QmpHotplugError> {
+        let cmd = QmpCommand::unplug_from_arg(arg)?;
+        let id = arg
+            .get_id()
+            .ok_or_else(|| QmpHotplugError::MissingId(arg.key().to_string()))?;
+        let wait_deleted = arg.key_equals("-device");
+
+        let (cmd, cmd_id) = self.assign_id(cmd);
+        let result = tokio::time::timeout(timeout, async {
+            self.send_command(&cmd).await?;
+            let mut replied = false;
+            let mut deleted = !wait_deleted;
+            while !(replied && deleted) {
+                match self.read_message().await? {
+                    QmpMessage::Reply(rep) if rep.id.as_ref() == Some(&cmd_id) => replied = true,
+                    QmpMessage::Error(err) if err.id.as_ref() == Some(&cmd_id) => {
+                        return Err(QmpExecuteError::Command(Box::new(err)));
+                    }
+                    QmpMessage::Event(ev)
+                        if ev.name == "DEVICE_DELETED"
+                            && ev
+                                .data
+                                .as_ref()
+                                .and_then(|d| d.get("device"))
+                                .and_then(|d| d.as_str())
+                                == Some(id.as_str()) =>
+                    {
+                        self.dispatch(&QmpMessage::Event(ev));
+                        deleted = true;
+                    }
+                    other => self.dispatch(&other),
+                }
+            }
+            Ok(())
+        })
+        .await
+        .unwrap_or(Err(QmpExecuteError::Timeout));
+        result?;
+
+        self.instance
+            .get_mut_args()
+            .remove_arg_by_id(arg.key(), &id);
+        Ok(())
+    }
+
     pub async fn system_powerdown(&mut self) -> Result<(), QmpSendError> {
         self.send_command(&QmpCommand::system_powerdown()).await
     }
@@ -91,3 +719,106 @@ where
         self.send_command(&QmpCommand::cont()).await
     }
 }
+
+fn split_unix_stream<R, W>(socket: UnixStream) -> (R, W)
+where
+    R: FromUnixHalf<OwnedReadHalf>,
+    W: FromUnixHalf<OwnedWriteHalf>,
+{
+    let (r, w) = socket.into_split();
+    (R::from_unix_half(r), W::from_unix_half(w))
+}
+
+async fn qmp_handshake<R, W>(
+    sender: &mut QmpSender<W>,
+    stream: &mut QmpMessageStream<R>,
+) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    match stream.next().await {
+        Some(QmpMessage::Greeting(_)) => {}
+        Some(other) => {
+            return Err(std::io::Error::other(format!(
+                "expected QMP greeting, got {:?}",
+                other
+            )));
+        }
+        None => return Err(std::io::Error::other("QMP socket closed before greeting")),
+    }
+
+    sender
+        .send(&QmpCommand::qmp_capabilities())
+        .await
+        .map_err(std::io::Error::other)?;
+
+    loop {
+        match stream.next().await {
+            Some(QmpMessage::Reply(_)) => return Ok(()),
+            Some(QmpMessage::Error(err)) => {
+                return Err(std::io::Error::other(format!(
+                    "qmp_capabilities failed: {}",
+                    err.desc()
+                )));
+            }
+            Some(_) => continue,
+            None => return Err(std::io::Error::other("QMP socket closed during handshake")),
+        }
+    }
+}
+
+/// Rebuilds the launch args of a running QEMU from `/proc/<pid>/cmdline`, dropping
+/// the monitor (a `-chardev`/`-mon` pair or a `-qmp unix:`) that serves `qmp_socket`.
+async fn read_qemu_cmdline(pid: u32, qmp_socket: &Path) -> std::io::Result<QemuLaunchArgs> {
+    let raw = tokio::fs::read(format!("/proc/{}/cmdline", pid)).await?;
+    // Every argument ends in a NUL; only the last terminator is dropped, so empty
+    // arguments (`-append ""`) are kept.
+    let argv: Vec<String> = raw
+        .strip_suffix(&[0])
+        .unwrap_or(&raw)
+        .split(|b| *b == 0)
+        .map(|a| String::from_utf8_lossy(a).into_owned())
+        .collect();
+
+    let is_qemu = argv
+        .first()
+        .and_then(|bin| Path::new(bin).file_name())
+        .is_some_and(|name| name.to_string_lossy().starts_with("qemu"));
+    if !is_qemu {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("process {} is not QEMU: {:?}", pid, argv.first()),
+        ));
+    }
+
+    let mut args = QemuLaunchArgs::parse_args_lenient(argv)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let socket = qmp_socket.to_string_lossy();
+    let monitor_ids: Vec<String> = args
+        .get_args()
+        .iter()
+        .filter(|a| a.key_equals("-chardev") && a.get_property("path").as_deref() == Some(&socket))
+        .filter_map(|a| a.get_id())
+        .collect();
+    args.get_mut_args().retain(|a| {
+        let chardev = match a.key() {
+            "-chardev" => a.get_id(),
+            "-mon" => a.get_property("chardev"),
+            "-qmp" | "-qmp-pretty" => return qmp_unix_path(a).as_deref() != Some(&*socket),
+            _ => return true,
+        };
+        !chardev.is_some_and(|id| monitor_ids.contains(&id))
+    });
+    Ok(args)
+}
+
+/// The socket path of a `-qmp unix:<path>[,server...]` argument.
+fn qmp_unix_path(arg: &QemuArg) -> Option<String> {
+    let rest = arg.to_args().get(1)?.strip_prefix("unix:")?.to_string();
+    let path = match QemuArg::from_opts("-chardev", &rest) {
+        QemuArg::List(_, items) => items.first()?.clone(),
+        _ => rest,
+    };
+    Some(path.replace(",,", ","))
+}
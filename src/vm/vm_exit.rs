@@ -0,0 +1,112 @@
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+
+use super::ShutdownStep;
+use crate::qmp::messages::QmpEvent;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum VmExitKind {
+    /// The guest powered itself off (or reset with `-no-reboot`).
+    GuestShutdown,
+    /// QEMU was asked to quit by the host (QMP `quit`, a signal QEMU handled, the UI).
+    HostQuit,
+    GuestPanic,
+    /// QEMU failed on its own: non-zero exit, or death by a signal we did not send.
+    QemuCrash,
+    /// We stopped QEMU with SIGTERM or SIGKILL.
+    Killed,
+}
+
+impl VmExitKind {
+    pub fn is_guest_problem(&self) -> bool {
+        matches!(self, VmExitKind::GuestPanic)
+    }
+
+    pub fn is_host_problem(&self) -> bool {
+        matches!(self, VmExitKind::QemuCrash)
+    }
+
+    /// Whether the exit counts as a failure for restart and alerting purposes.
+    pub fn is_failure(&self) -> bool {
+        self.is_guest_problem() || self.is_host_problem()
+    }
+}
+
+/// Everything known about how a QEMU process ended.
+#[derive(Debug, Clone)]
+pub struct VmExit {
+    pub kind: VmExitKind,
+    pub status: ExitStatus,
+    /// The last `SHUTDOWN` or `GUEST_PANICKED` event received before the exit.
+    pub event: Option<QmpEvent>,
+    pub stderr_tail: Vec<String>,
+}
+
+impl VmExit {
+    /// Classifies an exit. `requested` is the shutdown step we used, if we initiated it.
+    pub fn classify(
+        status: ExitStatus,
+        event: Option<QmpEvent>,
+        stderr_tail: Vec<String>,
+        requested: Option<ShutdownStep>,
+    ) -> Self {
+        let reason = event
+            .as_ref()
+            .and_then(|ev| ev.data.as_ref())
+            .and_then(|d| d.get("reason"))
+            .and_then(|r| r.as_str());
+        let panicked = event
+            .as_ref()
+            .is_some_and(|ev| ev.name == "GUEST_PANICKED" || reason == Some("guest-panic"));
+
+        let kind = if panicked {
+            VmExitKind::GuestPanic
+        } else if matches!(
+            requested,
+            Some(ShutdownStep::Sigterm | ShutdownStep::Sigkill)
+        ) {
+            VmExitKind::Killed
+        } else if status.signal().is_some() {
+            VmExitKind::QemuCrash
+        } else if let Some(ev) = event.as_ref().filter(|ev| ev.name == "SHUTDOWN") {
+            let guest = ev
+                .data
+                .as_ref()
+                .and_then(|d| d.get("guest"))
+                .and_then(|g| g.as_bool())
+                .unwrap_or_else(|| reason.is_some_and(|r| r.starts_with("guest-")));
+            if guest {
+                VmExitKind::GuestShutdown
+            } else {
+                VmExitKind::HostQuit
+            }
+        } else {
+            match requested {
+                Some(ShutdownStep::Quit) => VmExitKind::HostQuit,
+                Some(_) => VmExitKind::GuestShutdown,
+                None if status.success() => VmExitKind::GuestShutdown,
+                None => VmExitKind::QemuCrash,
+            }
+        };
+
+        Self {
+            kind,
+            status,
+            event,
+            stderr_tail,
+        }
+    }
+
+    pub fn code(&self) -> Option<i32> {
+        self.status.code()
+    }
+
+    /// The signal that terminated QEMU, if it died from one.
+    pub fn signal(&self) -> Option<i32> {
+        self.status.signal()
+    }
+
+    pub fn core_dumped(&self) -> bool {
+        self.status.core_dumped()
+    }
+}
@@ -1,23 +1,134 @@
-use crate::launcher::{QemuLaunchArgs, QemuProcess};
+use std::path::Path;
+
+use tokio::net::UnixStream;
+
+use super::qmp_monitor::QmpMonitor;
+use super::{QmpMonitorConfig, VmReadyError};
+use crate::launcher::json::QemuProfileVars;
+use crate::launcher::{
+    QemuLaunchArgs, QemuLaunchOptions, QemuOutput, QemuPreflight, QemuPreflightReport, QemuProcess,
+};
 
 #[derive(Debug)]
 pub struct VmInstance {
     args: QemuLaunchArgs,
+    options: QemuLaunchOptions,
     process: Option<QemuProcess>,
+    qmp_monitor_config: Option<QmpMonitorConfig>,
+    qmp_monitor: Option<QmpMonitor>,
+    qmp_stream: Option<UnixStream>,
+    preflight: Option<QemuPreflight>,
+    last_preflight: Option<QemuPreflightReport>,
+    profile_vars: Option<QemuProfileVars>,
 }
 
 impl VmInstance {
     pub fn new(args: QemuLaunchArgs) -> Self {
         Self {
             args,
+            options: QemuLaunchOptions::default(),
             process: None,
+            qmp_monitor_config: None,
+            qmp_monitor: None,
+            qmp_stream: None,
+            preflight: None,
+            last_preflight: None,
+            profile_vars: None,
         }
     }
 
+    /// An instance for a QEMU process that is already running (see `VmController::attach`).
+    pub(crate) fn from_process(args: QemuLaunchArgs, process: QemuProcess) -> Self {
+        Self {
+            process: Some(process),
+            ..Self::new(args)
+        }
+    }
+
+    pub fn with_launch_options(mut self, options: QemuLaunchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_qmp_monitor(mut self, config: QmpMonitorConfig) -> Self {
+        self.qmp_monitor_config = Some(config);
+        self
+    }
+
+    /// Runs these host checks before every launch; a failed check aborts the launch.
+    pub fn with_preflight(mut self, preflight: QemuPreflight) -> Self {
+        self.preflight = Some(preflight);
+        self
+    }
+
+    /// Substitutes `${...}` references in the args at every launch (the stored args
+    /// keep the references).
+    pub fn with_profile_vars(mut self, vars: QemuProfileVars) -> Self {
+        self.profile_vars = Some(vars);
+        self
+    }
+
+    /// Launches QEMU. When a QMP monitor is configured, a `-chardev socket` /
+    /// `-mon mode=control` pair is added to the spawned command line (the stored
+    /// args are left untouched) and this waits until the socket accepts a connection.
     pub async fn launch(&mut self) -> std::io::Result<()> {
-        let proc = QemuProcess::launch(&self.args).await?;
-        self.process = Some(proc);
-        Ok(())
+        Ok(self.try_launch().await?)
+    }
+
+    /// `launch`, reporting QEMU exiting before the QMP socket is up as `Exited`.
+    pub(crate) async fn try_launch(&mut self) -> Result<(), VmReadyError> {
+        self.release_qmp();
+        self.qmp_monitor = None;
+
+        let resolved = match &mut self.profile_vars {
+            Some(vars) => Some(
+                vars.resolve(&self.args)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?,
+            ),
+            None => None,
+        };
+        let mut args = resolved.as_ref().unwrap_or(&self.args);
+        self.last_preflight = self.preflight.as_ref().map(|p| p.run(args));
+        if let Some(report) = &self.last_preflight {
+            if !report.is_ok() {
+                return Err(VmReadyError::Launch(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("preflight checks failed:\n{}", report),
+                )));
+            }
+            for check in report.warnings() {
+                log::warn!("VmInstance: preflight: {}", check);
+            }
+            if let Some(adjusted) = report.get_adjusted_args() {
+                args = adjusted;
+            }
+        }
+
+        let Some(config) = &self.qmp_monitor_config else {
+            let proc = QemuProcess::launch_with_options(args, &self.options).await?;
+            self.process = Some(proc);
+            return Ok(());
+        };
+
+        let monitor = QmpMonitor::allocate(config, self.options.get_uid(), self.options.get_gid())?;
+        let args = monitor
+            .inject_into(args, config.get_chardev_id())
+            .map_err(std::io::Error::other)?;
+        let mut proc = QemuProcess::launch_with_options(&args, &self.options).await?;
+        match monitor.connect(&mut proc).await {
+            Ok(stream) => {
+                self.qmp_stream = Some(stream);
+                self.qmp_monitor = Some(monitor);
+                self.process = Some(proc);
+                Ok(())
+            }
+            Err(e) => {
+                // QEMU has usually exited already, so the kill may fail.
+                let _ = proc.kill().await;
+                monitor.remove();
+                Err(e)
+            }
+        }
     }
 
     pub fn get_args(&self) -> &QemuLaunchArgs {
@@ -28,6 +139,14 @@ impl VmInstance {
         &mut self.args
     }
 
+    pub fn get_launch_options(&self) -> &QemuLaunchOptions {
+        &self.options
+    }
+
+    pub fn get_mut_launch_options(&mut self) -> &mut QemuLaunchOptions {
+        &mut self.options
+    }
+
     pub fn get_process(&self) -> &Option<QemuProcess> {
         &self.process
     }
@@ -36,6 +155,52 @@ impl VmInstance {
         &mut self.process
     }
 
+    pub fn set_qmp_monitor(&mut self, config: Option<QmpMonitorConfig>) {
+        self.qmp_monitor_config = config;
+    }
+
+    pub fn get_qmp_monitor(&self) -> &Option<QmpMonitorConfig> {
+        &self.qmp_monitor_config
+    }
+
+    pub fn set_preflight(&mut self, preflight: Option<QemuPreflight>) {
+        self.preflight = preflight;
+    }
+
+    pub fn get_preflight(&self) -> &Option<QemuPreflight> {
+        &self.preflight
+    }
+
+    pub fn set_profile_vars(&mut self, vars: Option<QemuProfileVars>) {
+        self.profile_vars = vars;
+    }
+
+    /// Includes the values allocated for `${alloc.*}` references by the last launch.
+    pub fn get_profile_vars(&self) -> &Option<QemuProfileVars> {
+        &self.profile_vars
+    }
+
+    /// The report of the preflight checks run by the last `launch`.
+    pub fn last_preflight(&self) -> Option<&QemuPreflightReport> {
+        self.last_preflight.as_ref()
+    }
+
+    /// Path of the provisioned QMP socket while the VM is running.
+    pub fn qmp_socket_path(&self) -> Option<&Path> {
+        self.qmp_monitor.as_ref().map(|m| m.socket_path())
+    }
+
+    /// Takes the connection opened by `launch` to the provisioned QMP socket.
+    /// The QMP handshake has not been performed on it yet.
+    pub fn take_qmp_stream(&mut self) -> Option<UnixStream> {
+        self.qmp_stream.take()
+    }
+
+    /// Captured stdout/stderr of the current (or last) QEMU process.
+    pub fn output(&self) -> Option<&QemuOutput> {
+        self.process.as_ref().and_then(|p| p.get_output())
+    }
+
     pub fn is_running(&mut self) -> bool {
         match &mut self.process {
             Some(p) => p.is_running(),
@@ -49,19 +214,40 @@ impl VmInstance {
 
     pub async fn wait(&mut self) -> std::io::Result<std::process::ExitStatus> {
         match &mut self.process {
-            Some(p) => p.wait().await,
-            None => Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "VM is not running",
-            )),
+            Some(p) => {
+                let status = p.wait().await?;
+                self.release_qmp();
+                Ok(status)
+            }
+            None => Err(std::io::Error::other("VM is not running")),
         }
     }
 
+    /// SIGTERM, then SIGKILL if QEMU does not exit in time.
     pub async fn terminate(&mut self) -> std::io::Result<()> {
         if let Some(p) = &mut self.process {
-            p.terminate().await
-        } else {
-            Ok(())
+            p.terminate().await?;
+        }
+        self.release_qmp();
+        Ok(())
+    }
+
+    pub async fn kill(&mut self) -> std::io::Result<()> {
+        if let Some(p) = &mut self.process {
+            p.kill().await?;
+        }
+        self.release_qmp();
+        Ok(())
+    }
+
+    /// Drops the QMP connection, and removes the provisioned socket once QEMU has
+    /// exited. A QEMU that is still running keeps its socket so it can be attached to.
+    pub(crate) fn release_qmp(&mut self) {
+        self.qmp_stream = None;
+        if !self.is_running()
+            && let Some(monitor) = self.qmp_monitor.take()
+        {
+            monitor.remove();
         }
     }
 }
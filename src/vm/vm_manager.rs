@@ -1,18 +1,23 @@
 use std::collections::HashMap;
 use tokio::io::{AsyncRead, AsyncWrite};
 
-use super::VmController;
-use crate::launcher::QemuLaunchArgs;
+use super::{SupervisorConfig, VmController, VmExit, VmSupervisor};
+use crate::launcher::{QemuDiagnostic, QemuLaunchArgs};
 
-type VmCtrl = VmController<
-    Box<dyn AsyncRead + Unpin + Send + 'static>,
-    Box<dyn AsyncWrite + Unpin + Send + 'static>,
->;
+type VmReader = Box<dyn AsyncRead + Unpin + Send + 'static>;
+type VmWriter = Box<dyn AsyncWrite + Unpin + Send + 'static>;
+type VmCtrl = VmController<VmReader, VmWriter>;
 
 pub struct VmManager {
     vms: HashMap<String, VmCtrl>,
 }
 
+impl Default for VmManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl VmManager {
     pub fn new() -> Self {
         Self {
@@ -40,10 +45,46 @@ impl VmManager {
         self.vms.remove(name)
     }
 
-    pub async fn shutdown_all(&mut self) -> std::io::Result<()> {
-        for (_, vm) in &mut self.vms {
-            let _ = vm.terminate().await;
+    /// Runs `QemuLaunchArgs::validate_with` on a VM's args against all other VMs.
+    pub fn validate_vm(&self, name: &str) -> Option<Vec<QemuDiagnostic>> {
+        let vm = self.vms.get(name)?;
+        let others = self
+            .vms
+            .iter()
+            .filter(|(other, _)| other.as_str() != name)
+            .map(|(_, vm)| vm.get_instance().get_args());
+        Some(vm.get_instance().get_args().validate_with(others))
+    }
+
+    /// Collects VMs whose QEMU process has exited since the last call.
+    pub async fn reap_exited(&mut self) -> Vec<(String, VmExit)> {
+        let mut exited = Vec::new();
+        for (name, vm) in self.vms.iter_mut() {
+            if let Some(exit) = vm.poll_exit().await {
+                exited.push((name.clone(), exit));
+            }
         }
+        exited
+    }
+
+    /// Moves a VM out of the manager into a `VmSupervisor`. Put it back with
+    /// `insert_vm(name, supervisor.into_controller())` once supervision ends.
+    pub fn supervise(
+        &mut self,
+        name: &str,
+        config: SupervisorConfig,
+    ) -> Option<VmSupervisor<VmReader, VmWriter>> {
+        self.vms
+            .remove(name)
+            .map(|vm| VmSupervisor::new(vm, config))
+    }
+
+    pub fn insert_vm(&mut self, name: impl Into<String>, vm: VmCtrl) {
+        self.vms.insert(name.into(), vm);
+    }
+
+    pub async fn shutdown_all(&mut self) -> std::io::Result<()> {
+        futures::future::join_all(self.vms.values_mut().map(|vm| vm.terminate())).await;
         Ok(())
     }
-}
\ No newline at end of file
+}
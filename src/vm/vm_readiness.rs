@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use super::GuestReadiness;
+
+/// What `VmController::launch_and_wait_ready` waits for, and for how long.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct VmReadiness {
+    deadline: Duration,
+    guest: Option<GuestReadiness>,
+    /// How long QEMU must stay alive when there is no QMP monitor or guest signal to
+    /// wait for.
+    settle: Duration,
+}
+
+impl Default for VmReadiness {
+    fn default() -> Self {
+        Self {
+            deadline: Duration::from_secs(60),
+            guest: None,
+            settle: Duration::from_millis(500),
+        }
+    }
+}
+
+impl VmReadiness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overall time allowed from spawning QEMU until the VM counts as ready.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn with_guest(mut self, guest: GuestReadiness) -> Self {
+        self.guest = Some(guest);
+        self
+    }
+
+    pub fn with_settle(mut self, settle: Duration) -> Self {
+        self.settle = settle;
+        self
+    }
+
+    pub fn get_deadline(&self) -> Duration {
+        self.deadline
+    }
+
+    pub fn get_guest(&self) -> Option<&GuestReadiness> {
+        self.guest.as_ref()
+    }
+
+    pub fn get_settle(&self) -> Duration {
+        self.settle
+    }
+}
@@ -0,0 +1,73 @@
+use std::process::ExitStatus;
+
+/// The part of `VmController::launch_and_wait_ready` that did not finish in time.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum VmReadyStage {
+    Qmp,
+    Guest,
+}
+
+impl std::fmt::Display for VmReadyStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VmReadyStage::Qmp => write!(f, "QMP"),
+            VmReadyStage::Guest => write!(f, "guest readiness"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum VmReadyError {
+    /// QEMU could not be started, or the QMP handshake failed.
+    Launch(std::io::Error),
+    /// QEMU exited before the VM was ready.
+    Exited {
+        status: ExitStatus,
+        stderr: Vec<String>,
+    },
+    TimedOut(VmReadyStage),
+    /// The guest readiness check itself failed, e.g. its socket closed.
+    Guest(std::io::Error),
+}
+
+impl std::fmt::Display for VmReadyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VmReadyError::Launch(e) => write!(f, "Launch failed: {}", e),
+            VmReadyError::Exited { status, stderr } => {
+                write!(f, "QEMU exited during startup: {}", status)?;
+                for line in stderr {
+                    write!(f, "\n{}", line)?;
+                }
+                Ok(())
+            }
+            VmReadyError::TimedOut(stage) => write!(f, "Timed out waiting for {}", stage),
+            VmReadyError::Guest(e) => write!(f, "Guest readiness check failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VmReadyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VmReadyError::Launch(e) | VmReadyError::Guest(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for VmReadyError {
+    fn from(e: std::io::Error) -> Self {
+        VmReadyError::Launch(e)
+    }
+}
+
+impl From<VmReadyError> for std::io::Error {
+    fn from(e: VmReadyError) -> Self {
+        match e {
+            VmReadyError::Launch(e) => e,
+            VmReadyError::TimedOut(_) => std::io::Error::new(std::io::ErrorKind::TimedOut, e),
+            e => std::io::Error::other(e),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    FromUnixHalf, RestartPolicy, SupervisorConfig, VmController, VmExit, VmLifecycleEvent,
+};
+
+type LifecycleHandler = Box<dyn Fn(&VmLifecycleEvent) + Send + Sync + 'static>;
+
+#[derive(Debug, Clone)]
+pub enum VmSupervisorOutcome {
+    /// The VM exited and the restart policy said not to restart it.
+    Finished(VmExit),
+    /// Restarting was abandoned (retry limit, crash loop, or QEMU could not be watched).
+    GaveUp(String),
+    /// The cancellation token was triggered; the VM was shut down.
+    Stopped,
+}
+
+/// Launches a VM and keeps it running according to a `RestartPolicy`.
+///
+/// The supervisor owns its `VmController` while `run` is active; use
+/// `get_mut_controller` or `into_controller` before or after that.
+pub struct VmSupervisor<R, W>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    controller: VmController<R, W>,
+    config: SupervisorConfig,
+    handlers: Vec<LifecycleHandler>,
+    cancel: CancellationToken,
+}
+
+impl<R, W> VmSupervisor<R, W>
+where
+    R: AsyncRead + Unpin + Send + 'static + FromUnixHalf<OwnedReadHalf>,
+    W: AsyncWrite + Unpin + Send + 'static + FromUnixHalf<OwnedWriteHalf>,
+{
+    pub fn new(controller: VmController<R, W>, config: SupervisorConfig) -> Self {
+        Self {
+            controller,
+            config,
+            handlers: Vec::new(),
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    pub fn with_event_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&VmLifecycleEvent) + Send + Sync + 'static,
+    {
+        self.handlers.push(Box::new(handler));
+        self
+    }
+
+    pub fn get_controller(&self) -> &VmController<R, W> {
+        &self.controller
+    }
+
+    pub fn get_mut_controller(&mut self) -> &mut VmController<R, W> {
+        &mut self.controller
+    }
+
+    pub fn into_controller(self) -> VmController<R, W> {
+        self.controller
+    }
+
+    pub fn get_config(&self) -> &SupervisorConfig {
+        &self.config
+    }
+
+    /// Cancelling this token makes `run` shut the VM down and return `Stopped`. A
+    /// launch in progress is completed first so the new QEMU is shut down too.
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    fn emit(&self, event: VmLifecycleEvent) {
+        for handler in &self.handlers {
+            handler(&event);
+        }
+    }
+
+    pub async fn run(&mut self) -> VmSupervisorOutcome {
+        let mut attempt = 0;
+        let mut consecutive_restarts = 0;
+        let mut failures: VecDeque<Instant> = VecDeque::new();
+
+        loop {
+            attempt += 1;
+            let started = Instant::now();
+
+            // Not raced against the token: dropping a half-done launch would leave
+            // QEMU running with nobody tracking it.
+            let launched = self.controller.launch().await;
+            if self.cancel.is_cancelled() {
+                return self.stop().await;
+            }
+
+            let exit = match launched {
+                Ok(()) => {
+                    self.emit(VmLifecycleEvent::Started {
+                        attempt,
+                        pid: self.controller.get_instance().pid(),
+                    });
+                    let exit = tokio::select! {
+                        exit = self.controller.wait_exit() => exit,
+                        _ = self.cancel.cancelled() => return self.stop().await,
+                    };
+                    match exit {
+                        Ok(exit) => {
+                            self.emit(VmLifecycleEvent::Exited {
+                                attempt,
+                                exit: exit.clone(),
+                            });
+                            Some(exit)
+                        }
+                        Err(e) => {
+                            return self.give_up(format!("lost track of QEMU: {}", e));
+                        }
+                    }
+                }
+                Err(e) => {
+                    self.emit(VmLifecycleEvent::LaunchFailed {
+                        attempt,
+                        error: e.to_string(),
+                    });
+                    None
+                }
+            };
+
+            let failed = exit.as_ref().is_none_or(|e| e.kind.is_failure());
+            if started.elapsed() >= self.config.get_stable_after() {
+                consecutive_restarts = 0;
+            }
+
+            match self.config.get_restart_policy() {
+                RestartPolicy::Never => {
+                    return match exit {
+                        Some(exit) => VmSupervisorOutcome::Finished(exit),
+                        None => self.give_up("launch failed".to_string()),
+                    };
+                }
+                RestartPolicy::OnFailure { .. } if !failed => {
+                    if let Some(exit) = exit {
+                        return VmSupervisorOutcome::Finished(exit);
+                    }
+                }
+                RestartPolicy::OnFailure { max_retries } if consecutive_restarts >= max_retries => {
+                    return self.give_up(format!("failed {} times in a row", max_retries + 1));
+                }
+                _ => {}
+            }
+
+            if failed {
+                let now = Instant::now();
+                failures.push_back(now);
+                while failures
+                    .front()
+                    .is_some_and(|t| now.duration_since(*t) > self.config.get_crash_loop_window())
+                {
+                    failures.pop_front();
+                }
+                if failures.len() >= self.config.get_crash_loop_limit() {
+                    return self.give_up(format!(
+                        "crash loop: {} failures within {:?}",
+                        failures.len(),
+                        self.config.get_crash_loop_window()
+                    ));
+                }
+            }
+
+            consecutive_restarts += 1;
+            let delay = self.config.backoff_for(consecutive_restarts);
+            self.emit(VmLifecycleEvent::RestartScheduled {
+                attempt: attempt + 1,
+                delay,
+            });
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = self.cancel.cancelled() => return self.stop().await,
+            }
+        }
+    }
+
+    async fn stop(&mut self) -> VmSupervisorOutcome {
+        if let Err(e) = self.controller.shutdown().await {
+            log::warn!("VmSupervisor: shutdown failed: {}", e);
+        }
+        self.emit(VmLifecycleEvent::Stopped);
+        VmSupervisorOutcome::Stopped
+    }
+
+    fn give_up(&self, reason: String) -> VmSupervisorOutcome {
+        self.emit(VmLifecycleEvent::GaveUp {
+            reason: reason.clone(),
+        });
+        VmSupervisorOutcome::GaveUp(reason)
+    }
+}